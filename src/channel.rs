@@ -1,12 +1,13 @@
 //! Low-level LDAP channel operations
 
-use std::{io, net::ToSocketAddrs, time::Duration};
+use std::{fmt, io, net::SocketAddr, time::Duration};
 
 use futures::{
     StreamExt, TryStreamExt,
     channel::mpsc::{self, Receiver, Sender},
     future,
     sink::SinkExt,
+    stream::FuturesUnordered,
 };
 use log::debug;
 use rasn_ldap::LdapMessage;
@@ -23,7 +24,9 @@ use crate::{
 };
 
 const CHANNEL_SIZE: usize = 1024;
-const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub(crate) const DEFAULT_STARTTLS_TIMEOUT: Duration = Duration::from_secs(30);
+pub(crate) const DEFAULT_CONNECT_STAGGER: Duration = Duration::from_millis(250);
 
 pub type LdapMessageSender = Sender<LdapMessage>;
 pub type LdapMessageReceiver = Receiver<LdapMessage>;
@@ -99,6 +102,9 @@ pub enum ChannelError {
     #[error("STARTTLS failed")]
     StartTlsFailed,
 
+    #[error("{}", ConnectFailures(.0))]
+    ConnectFailed(Vec<(SocketAddr, ChannelError)>),
+
     #[cfg(feature = "tls-native-tls")]
     #[error(transparent)]
     NativeTls(#[from] native_tls::Error),
@@ -114,10 +120,39 @@ pub enum ChannelError {
 
 pub type ChannelResult<T> = Result<T, ChannelError>;
 
+struct ConnectFailures<'a>(&'a [(SocketAddr, ChannelError)]);
+
+impl fmt::Display for ConnectFailures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connection failed")?;
+        for (i, (address, error)) in self.0.iter().enumerate() {
+            write!(f, "{} {address}: {error}", if i == 0 { ":" } else { ";" })?;
+        }
+        Ok(())
+    }
+}
+
+// Alternate the address families, starting with the family of the first address (RFC8305)
+fn interleave_addrs(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|a| a.is_ipv6() == first_v6);
+    let mut result = Vec::with_capacity(preferred.len() + other.len());
+    let mut other = other.into_iter();
+    for addr in preferred {
+        result.push(addr);
+        result.extend(other.next());
+    }
+    result.extend(other);
+    result
+}
+
 /// LDAP TCP channel connector
 pub struct LdapChannel {
     address: String,
     port: u16,
+    connect_timeout: Duration,
+    starttls_timeout: Duration,
+    connect_stagger: Duration,
 }
 
 impl LdapChannel {
@@ -129,19 +164,42 @@ impl LdapChannel {
         LdapChannel {
             address: address.as_ref().to_owned(),
             port,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            starttls_timeout: DEFAULT_STARTTLS_TIMEOUT,
+            connect_stagger: DEFAULT_CONNECT_STAGGER,
         }
     }
 
+    /// Set TCP connect timeout for every resolved address, default is 10 seconds
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the delay before the connection to the next resolved address is started
+    /// while the previous attempts are still pending, default is 250 milliseconds
+    pub fn connect_stagger(mut self, stagger: Duration) -> Self {
+        self.connect_stagger = stagger;
+        self
+    }
+
+    /// Set the timeout for the STARTTLS response, default is 30 seconds
+    pub fn starttls_timeout(mut self, timeout: Duration) -> Self {
+        self.starttls_timeout = timeout;
+        self
+    }
+
     /// Connect to a server
     /// Returns a pair of (sender, receiver) endpoints
     pub async fn connect(self, tls_options: TlsOptions) -> ChannelResult<(LdapMessageSender, LdapMessageReceiver)> {
-        let mut addrs = (self.address.as_ref(), self.port).to_socket_addrs()?;
-        let address = addrs.next().ok_or_else(|| io_error("Address resolution error"))?;
-
-        // TCP connect with a timeout
-        let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&address)).await??;
+        let addrs = tokio::net::lookup_host((self.address.as_str(), self.port))
+            .await?
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            return Err(io_error("Address resolution error").into());
+        }
 
-        debug!("Connection established to {address}");
+        let stream = self.connect_addrs(interleave_addrs(addrs)).await?;
 
         let channel = match tls_options.kind {
             TlsKind::Plain => make_channel(stream),
@@ -153,6 +211,54 @@ impl LdapChannel {
         Ok(channel)
     }
 
+    async fn connect_addr(&self, address: SocketAddr) -> (SocketAddr, ChannelResult<TcpStream>) {
+        debug!("Connecting to {address}");
+        let result = match tokio::time::timeout(self.connect_timeout, TcpStream::connect(address)).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(e) => Err(e.into()),
+        };
+        (address, result)
+    }
+
+    // Happy eyeballs (RFC8305): the next address is tried when the previous attempt fails
+    // or does not complete within the stagger delay, the first established connection wins
+    async fn connect_addrs(&self, addrs: Vec<SocketAddr>) -> ChannelResult<TcpStream> {
+        let mut addrs = addrs.into_iter().peekable();
+        let mut attempts = FuturesUnordered::new();
+        let mut failures = Vec::new();
+
+        attempts.extend(addrs.next().map(|address| self.connect_addr(address)));
+
+        while !attempts.is_empty() {
+            let result = if addrs.peek().is_some() {
+                match tokio::time::timeout(self.connect_stagger, attempts.next()).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        attempts.extend(addrs.next().map(|address| self.connect_addr(address)));
+                        continue;
+                    }
+                }
+            } else {
+                attempts.next().await
+            };
+
+            match result {
+                Some((address, Ok(stream))) => {
+                    debug!("Connection established to {address}");
+                    return Ok(stream);
+                }
+                Some((address, Err(e))) => {
+                    debug!("Connection to {address} failed: {e}");
+                    failures.push((address, e));
+                    attempts.extend(addrs.next().map(|address| self.connect_addr(address)));
+                }
+                None => break,
+            }
+        }
+
+        Err(ChannelError::ConnectFailed(failures))
+    }
+
     async fn tls_connect<S>(&self, tls_options: TlsOptions, stream: S) -> ChannelResult<Box<dyn TlsStream>>
     where
         S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
@@ -183,8 +289,6 @@ impl LdapChannel {
         use log::warn;
         use rasn_ldap::{ExtendedRequest, ProtocolOp, ResultCode};
 
-        const STARTTLS_TIMEOUT: Duration = Duration::from_secs(30);
-
         debug!("Begin STARTTLS negotiation");
         let mut framed = tokio_util::codec::Framed::new(&mut stream, LdapCodec);
         let req = ExtendedRequest {
@@ -195,7 +299,7 @@ impl LdapChannel {
             .send(LdapMessage::new(1, ProtocolOp::ExtendedReq(req)))
             .await
             .map_err(|_| ChannelError::StartTlsFailed)?;
-        match tokio::time::timeout(STARTTLS_TIMEOUT, framed.next()).await {
+        match tokio::time::timeout(self.starttls_timeout, framed.next()).await {
             Ok(Some(Ok(item))) => match item.protocol_op {
                 ProtocolOp::ExtendedResp(resp) if resp.result_code == ResultCode::Success && item.message_id == 1 => {
                     debug!("End STARTTLS negotiation, switching protocols");
@@ -338,4 +442,41 @@ mod tests {
 
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_interleave_addrs() {
+        let addrs = ["[::1]:389", "[::2]:389", "[::3]:389", "10.0.0.1:389", "10.0.0.2:389"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect::<Vec<SocketAddr>>();
+        let expected = ["[::1]:389", "10.0.0.1:389", "[::2]:389", "10.0.0.2:389", "[::3]:389"];
+        let result = interleave_addrs(addrs)
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(result, expected);
+    }
+
+    async fn closed_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connect_addrs() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addrs = vec![closed_addr().await, listener.local_addr().unwrap()];
+        let channel = LdapChannel::for_client("localhost", 389).connect_stagger(Duration::from_millis(10));
+        assert!(channel.connect_addrs(addrs).await.is_ok());
+
+        let mut addrs = vec![closed_addr().await, closed_addr().await];
+        match channel.connect_addrs(addrs.clone()).await {
+            Err(ChannelError::ConnectFailed(failures)) => {
+                let mut failed = failures.iter().map(|f| f.0).collect::<Vec<_>>();
+                failed.sort();
+                addrs.sort();
+                assert_eq!(failed, addrs);
+            }
+            _ => panic!("Unexpected result"),
+        }
+    }
 }
@@ -1,48 +1,98 @@
 //! LDAP client module
 
 use std::{
-    collections::VecDeque,
     convert::{TryFrom, TryInto},
     pin::Pin,
     sync::{
         Arc,
-        atomic::{AtomicBool, AtomicU32, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
     },
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures::{Future, Stream, TryStreamExt, future::BoxFuture};
+use log::debug;
 use parking_lot::RwLock;
 use rasn_ldap::{
-    AuthenticationChoice, BindRequest, BindResponse, Controls, ExtendedRequest, LdapMessage, LdapResult, ProtocolOp,
-    ResultCode, SaslCredentials, SearchResultDone, UnbindRequest,
+    AttributeValueAssertion, AuthenticationChoice, BindRequest, BindResponse, CompareRequest, Control, Controls,
+    ExtendedRequest, LdapMessage, LdapResult, ProtocolOp, ResultCode, SaslCredentials, SearchResultDone, UnbindRequest,
 };
+use tokio::time::Sleep;
 
 use crate::{
-    Attribute, ModifyRequest, SearchEntry,
-    conn::{LdapConnection, MessageStream},
-    controls::SimplePagedResultsControl,
+    Attribute, ModifyDnRequest, ModifyRequest, OperationResult, SearchEntry, SearchItem, VirtualListView,
+    channel::{DEFAULT_CONNECT_STAGGER, DEFAULT_CONNECT_TIMEOUT, DEFAULT_STARTTLS_TIMEOUT, LdapChannel},
+    conn::{AbandonGuard, LdapConnection, MessageStream},
+    controls::{
+        DirSyncControl, PersistentSearchControl, ResponseControl, ServerSideSortControl, SimplePagedResultsControl,
+        SortResultControl, SyncRequestControl, VirtualListViewControl, VirtualListViewResponseControl,
+    },
+    dirsync::DirSyncEntries,
     error::Error,
-    oid,
+    extended::{ExtendedOperation, ExtendedResponse, PasswordModify, WhoAmI},
     options::TlsOptions,
-    request::SearchRequest,
+    psearch::ChangedEntries,
+    referral::ReferralEntries,
+    request::{OperationOptions, SearchRequest},
+    resolver::{Resolver, SystemResolver, sort_srv_records},
+    sync::SyncEntries,
+    url::LdapUrl,
 };
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-fn check_result(result: LdapResult) -> Result<()> {
+fn check_result(result: LdapResult, controls: Option<Controls>) -> Result<OperationResult> {
     if result.result_code == ResultCode::Success || result.result_code == ResultCode::SaslBindInProgress {
-        Ok(())
+        Ok(OperationResult::new(result, controls))
     } else {
-        Err(Error::OperationFailed(result.into()))
+        Err(Error::OperationFailed(OperationResult::new(result, controls).into()))
+    }
+}
+
+// Connection and operation timeouts of the client
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Timeouts {
+    pub(crate) connect: Duration,
+    pub(crate) connect_stagger: Duration,
+    pub(crate) starttls: Duration,
+    pub(crate) operation: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: DEFAULT_CONNECT_TIMEOUT,
+            connect_stagger: DEFAULT_CONNECT_STAGGER,
+            starttls: DEFAULT_STARTTLS_TIMEOUT,
+            operation: None,
+        }
     }
 }
 
+/// Order in which the servers are tried when connecting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailoverPolicy {
+    /// Always start with the first server
+    #[default]
+    InOrder,
+    /// Start with the next server on every connect
+    RoundRobin,
+}
+
 /// LDAP client builder
+#[derive(Clone)]
 pub struct LdapClientBuilder {
     address: String,
     port: u16,
+    servers: Vec<(String, u16)>,
+    srv_domain: Option<String>,
+    resolver: Arc<dyn Resolver>,
+    failover: FailoverPolicy,
+    next_server: Arc<AtomicUsize>,
     tls_options: TlsOptions,
+    default_controls: Vec<Control>,
+    timeouts: Timeouts,
 }
 
 impl LdapClientBuilder {
@@ -52,65 +102,339 @@ impl LdapClientBuilder {
         self
     }
 
+    /// Add a fallback server which is tried if the previous ones are not available
+    pub fn server<A: AsRef<str>>(mut self, address: A, port: u16) -> Self {
+        self.servers.push((address.as_ref().to_owned(), port));
+        self
+    }
+
+    /// Discover the servers from the `_ldap._tcp.<domain>` DNS SRV records.
+    /// If the lookup fails or returns no records, the builder address is used instead
+    pub fn srv_domain<D: AsRef<str>>(mut self, domain: D) -> Self {
+        self.srv_domain = Some(domain.as_ref().to_owned());
+        self
+    }
+
+    /// Set the resolver used for the server discovery, default is [SystemResolver]
+    pub fn resolver<R: Resolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Arc::new(resolver);
+        self
+    }
+
+    /// Set the order in which the servers are tried, default is [FailoverPolicy::InOrder]
+    pub fn failover(mut self, failover: FailoverPolicy) -> Self {
+        self.failover = failover;
+        self
+    }
+
     /// Set TLS options, default is plain connection
     pub fn tls_options(mut self, options: TlsOptions) -> Self {
         self.tls_options = options;
         self
     }
 
-    /// Build client and connect
+    /// Set the default controls which are sent with every request
+    pub fn default_controls<I>(mut self, controls: I) -> Self
+    where
+        I: IntoIterator<Item = Control>,
+    {
+        self.default_controls = controls.into_iter().collect();
+        self
+    }
+
+    /// Set TCP connect timeout for every server address, default is 10 seconds
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = timeout;
+        self
+    }
+
+    /// Set the delay between the connection attempts to the addresses of a server which resolves
+    /// to several IPv6 and IPv4 addresses, default is 250 milliseconds
+    pub fn connect_stagger(mut self, stagger: Duration) -> Self {
+        self.timeouts.connect_stagger = stagger;
+        self
+    }
+
+    /// Set the timeout for the STARTTLS negotiation, default is 30 seconds
+    pub fn starttls_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.starttls = timeout;
+        self
+    }
+
+    /// Set the default operation timeout, default is no timeout. For search operations it limits
+    /// the time to wait for each response. An operation which times out is abandoned and fails with [Error::Timeout].
+    /// A bind cannot be abandoned, so a bind timeout closes the connection
+    pub fn operation_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.operation = Some(timeout);
+        self
+    }
+
+    async fn resolve_servers(&self) -> Vec<(String, u16)> {
+        let mut servers = Vec::new();
+        if let Some(ref domain) = self.srv_domain {
+            match self.resolver.lookup_srv(&format!("_ldap._tcp.{domain}")).await {
+                Ok(records) => servers.extend(sort_srv_records(records).into_iter().map(|r| (r.target, r.port))),
+                Err(e) => debug!("SRV lookup for {domain} failed: {e}"),
+            }
+        }
+        if servers.is_empty() {
+            servers.push((self.address.clone(), self.port));
+        }
+        servers.extend(self.servers.iter().cloned());
+        servers
+    }
+
+    /// Build client and connect. The servers are tried according to the [FailoverPolicy]
+    /// and the error of the last one is returned if none is available
     pub async fn connect(self) -> Result<LdapClient> {
-        LdapClient::connect(self.address, self.port, self.tls_options).await
+        let servers = self.resolve_servers().await;
+        let start = match self.failover {
+            FailoverPolicy::InOrder => 0,
+            FailoverPolicy::RoundRobin => self.next_server.fetch_add(1, Ordering::SeqCst) % servers.len(),
+        };
+
+        let mut last_error = None;
+        for (address, port) in servers.iter().cycle().skip(start).take(servers.len()) {
+            match LdapClient::connect(address, *port, self.tls_options.clone(), self.timeouts).await {
+                Ok(mut client) => {
+                    client.default_controls = self.default_controls;
+                    return Ok(client);
+                }
+                Err(e) => {
+                    debug!("Connection to {address}:{port} failed: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("No servers"))
     }
 }
 
+// Credentials and request controls of the last successful bind, used to authenticate follow-up connections
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) enum BindCredentials {
+    Simple {
+        username: String,
+        password: String,
+        controls: Vec<Control>,
+    },
+    SaslExternal {
+        controls: Vec<Control>,
+    },
+    #[cfg(feature = "gssapi")]
+    SaslGssApi {
+        realm: String,
+    },
+}
+
 /// LDAP client
 #[derive(Clone)]
 pub struct LdapClient {
     connection: LdapConnection,
-    id_counter: Arc<AtomicU32>,
+    default_controls: Vec<Control>,
+    tls_options: Arc<TlsOptions>,
+    bind_credentials: Arc<RwLock<Option<BindCredentials>>>,
+    timeouts: Timeouts,
 }
 
 impl LdapClient {
+    /// Create a client builder from the LDAP URL. The URL scheme selects TLS options and the default port
+    pub fn builder_from_url<S: AsRef<str>>(url: S) -> Result<LdapClientBuilder> {
+        LdapClientBuilder::try_from(&url.as_ref().parse::<LdapUrl>()?)
+    }
+
+    /// Create a client builder from a list of LDAP URLs, the servers are tried in the given order.
+    /// All URLs must have the same scheme
+    pub fn builder_from_urls<I, S>(urls: I) -> Result<LdapClientBuilder>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let urls = urls
+            .into_iter()
+            .map(|url| url.as_ref().parse::<LdapUrl>())
+            .collect::<Result<Vec<_>>>()?;
+        let (first, rest) = urls
+            .split_first()
+            .ok_or_else(|| Error::InvalidUrl("No URLs".to_owned()))?;
+
+        let mut builder = LdapClientBuilder::try_from(first)?;
+        for url in rest {
+            match (url.scheme == first.scheme, url.host.as_ref(), url.port_or_default()) {
+                (true, Some(host), Some(port)) => builder = builder.server(host, port),
+                _ => return Err(Error::InvalidUrl(url.to_string())),
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Create a client builder which discovers the servers of a given domain from DNS SRV records.
+    /// Active Directory domain controllers are registered this way
+    pub fn builder_from_domain<D: AsRef<str>>(domain: D) -> LdapClientBuilder {
+        Self::builder(domain.as_ref()).srv_domain(domain)
+    }
+
     /// Create a client builder
     pub fn builder<A: AsRef<str>>(address: A) -> LdapClientBuilder {
         LdapClientBuilder {
             address: address.as_ref().to_owned(),
             port: 389,
+            servers: Vec::new(),
+            srv_domain: None,
+            resolver: Arc::new(SystemResolver),
+            failover: FailoverPolicy::default(),
+            next_server: Arc::new(AtomicUsize::new(0)),
             tls_options: TlsOptions::default(),
+            default_controls: Vec::new(),
+            timeouts: Timeouts::default(),
         }
     }
 
-    pub(crate) async fn connect<A>(address: A, port: u16, tls_options: TlsOptions) -> Result<Self>
+    pub(crate) async fn connect<A>(address: A, port: u16, tls_options: TlsOptions, timeouts: Timeouts) -> Result<Self>
     where
         A: AsRef<str>,
     {
-        let connection = LdapConnection::connect(address, port, tls_options).await?;
+        let channel = LdapChannel::for_client(address, port)
+            .connect_timeout(timeouts.connect)
+            .connect_stagger(timeouts.connect_stagger)
+            .starttls_timeout(timeouts.starttls);
+        let connection = LdapConnection::connect(channel, tls_options.clone()).await?;
         Ok(Self {
             connection,
-            id_counter: Arc::new(AtomicU32::new(2)), // 1 is used by STARTTLS
+            default_controls: Vec::new(),
+            tls_options: Arc::new(tls_options),
+            bind_credentials: Arc::new(RwLock::new(None)),
+            timeouts,
         })
     }
 
+    pub(crate) fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    /// Return a client instance sharing the connection with this one, which uses a given operation timeout
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.timeouts.operation = Some(timeout);
+        client
+    }
+
+    pub(crate) fn tls_options(&self) -> &TlsOptions {
+        &self.tls_options
+    }
+
+    pub(crate) fn default_controls(&self) -> &[Control] {
+        &self.default_controls
+    }
+
+    // Restore the default controls and timeouts of a given builder
+    pub(crate) fn reset_defaults(&mut self, builder: &LdapClientBuilder) {
+        self.default_controls.clone_from(&builder.default_controls);
+        self.timeouts = builder.timeouts;
+    }
+
+    pub(crate) fn bind_credentials(&self) -> Option<BindCredentials> {
+        self.bind_credentials.read().clone()
+    }
+
+    pub(crate) async fn rebind(&mut self, credentials: BindCredentials) -> Result<()> {
+        match credentials {
+            BindCredentials::Simple {
+                username,
+                password,
+                controls,
+            } => {
+                let options = OperationOptions {
+                    controls,
+                    ..Default::default()
+                };
+                self.simple_bind_with_options(username, password, options).await?;
+            }
+            BindCredentials::SaslExternal { controls } => {
+                let options = OperationOptions {
+                    controls,
+                    ..Default::default()
+                };
+                self.sasl_external_bind_with_options(options).await?;
+            }
+            #[cfg(feature = "gssapi")]
+            BindCredentials::SaslGssApi { realm } => {
+                self.sasl_gssapi_bind(realm).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Restore the given bind identity, `None` stands for the anonymous bind
+    pub(crate) async fn reset_bind(&mut self, credentials: Option<BindCredentials>) -> Result<()> {
+        match credentials {
+            Some(credentials) => self.rebind(credentials).await,
+            None => {
+                let req = BindRequest::new(3, String::new().into(), AuthenticationChoice::Simple(Vec::new().into()));
+                self.do_bind(req).await?;
+                *self.bind_credentials.write() = None;
+                Ok(())
+            }
+        }
+    }
+
+    /// Return true if the underlying connection is closed
+    pub fn is_closed(&self) -> bool {
+        self.connection.is_closed()
+    }
+
+    /// Replace the default controls which are sent with every request made by this client instance
+    pub fn set_default_controls<I>(&mut self, controls: I)
+    where
+        I: IntoIterator<Item = Control>,
+    {
+        self.default_controls = controls.into_iter().collect();
+    }
+
     fn new_id(&self) -> u32 {
-        self.id_counter.fetch_add(1, Ordering::SeqCst)
+        self.connection.new_id()
+    }
+
+    fn new_message(&self, protocol_op: ProtocolOp, controls: Vec<Control>) -> LdapMessage {
+        let mut msg = LdapMessage::new(self.new_id(), protocol_op);
+        let controls = self
+            .default_controls
+            .iter()
+            .cloned()
+            .chain(controls)
+            .collect::<Vec<_>>();
+        if !controls.is_empty() {
+            msg.controls = Some(controls);
+        }
+        msg
+    }
+
+    async fn do_bind(&mut self, req: BindRequest) -> Result<(OperationResult, BindResponse)> {
+        self.do_bind_with_options(req, OperationOptions::default()).await
     }
 
-    async fn do_bind(&mut self, req: BindRequest) -> Result<BindResponse> {
-        let id = self.new_id();
-        let msg = LdapMessage::new(id, ProtocolOp::BindRequest(req));
+    async fn do_bind_with_options(
+        &mut self,
+        req: BindRequest,
+        options: OperationOptions,
+    ) -> Result<(OperationResult, BindResponse)> {
+        let msg = self.new_message(ProtocolOp::BindRequest(req), options.controls);
 
-        let item = self.connection.send_recv(msg).await?;
+        // the server resets the connection to anonymous when a bind request is received (RFC4511 4.2.1)
+        *self.bind_credentials.write() = None;
+
+        let timeout = options.timeout.or(self.timeouts.operation);
+        let item = self.connection.send_recv(msg, timeout).await?;
 
         match item.protocol_op {
             ProtocolOp::BindResponse(resp) => {
-                let result = resp.clone();
-                check_result(LdapResult::new(
+                let mut result = LdapResult::new(
                     resp.result_code,
-                    resp.matched_dn,
-                    resp.diagnostic_message,
-                ))?;
-                Ok(result)
+                    resp.matched_dn.clone(),
+                    resp.diagnostic_message.clone(),
+                );
+                result.referral = resp.referral.clone();
+                Ok((check_result(result, item.controls)?, resp))
             }
             _ => Err(Error::InvalidResponse),
         }
@@ -123,22 +447,50 @@ impl LdapClient {
     }
 
     /// Perform a simple bind operation with username and password
-    pub async fn simple_bind<U, P>(&mut self, username: U, password: P) -> Result<()>
+    pub async fn simple_bind<U, P>(&mut self, username: U, password: P) -> Result<OperationResult>
+    where
+        U: AsRef<str>,
+        P: AsRef<str>,
+    {
+        self.simple_bind_with_options(username, password, OperationOptions::default())
+            .await
+    }
+
+    /// Perform a simple bind operation with username, password and additional options
+    pub async fn simple_bind_with_options<U, P>(
+        &mut self,
+        username: U,
+        password: P,
+        options: OperationOptions,
+    ) -> Result<OperationResult>
     where
         U: AsRef<str>,
         P: AsRef<str>,
     {
         let auth_choice = AuthenticationChoice::Simple(password.as_ref().as_bytes().into());
         let req = BindRequest::new(3, username.as_ref().to_owned().into(), auth_choice);
-        self.do_bind(req).await?;
-        Ok(())
+        let controls = options.controls.clone();
+        let result = self.do_bind_with_options(req, options).await?.0;
+        *self.bind_credentials.write() = Some(BindCredentials::Simple {
+            username: username.as_ref().to_owned(),
+            password: password.as_ref().to_owned(),
+            controls,
+        });
+        Ok(result)
     }
 
     /// Perform SASL EXTERNAL bind
-    pub async fn sasl_external_bind(&mut self) -> Result<()> {
+    pub async fn sasl_external_bind(&mut self) -> Result<OperationResult> {
+        self.sasl_external_bind_with_options(OperationOptions::default()).await
+    }
+
+    /// Perform SASL EXTERNAL bind with additional options
+    pub async fn sasl_external_bind_with_options(&mut self, options: OperationOptions) -> Result<OperationResult> {
         let req = self.new_sasl_bind_req("EXTERNAL", None);
-        self.do_bind(req).await?;
-        Ok(())
+        let controls = options.controls.clone();
+        let result = self.do_bind_with_options(req, options).await?.0;
+        *self.bind_credentials.write() = Some(BindCredentials::SaslExternal { controls });
+        Ok(result)
     }
 
     #[cfg(feature = "gssapi")]
@@ -146,7 +498,7 @@ impl LdapClient {
     /// The following features are NOT implemented:
     ///  * SASL protection over plain connection (use TLS instead)
     ///  * Channel binding
-    pub async fn sasl_gssapi_bind<S: AsRef<str>>(&mut self, realm: S) -> Result<()> {
+    pub async fn sasl_gssapi_bind<S: AsRef<str>>(&mut self, realm: S) -> Result<OperationResult> {
         // GSSAPI code credits: https://github.com/inejge/ldap3
         use cross_krb5::{ClientCtx, InitiateFlags, K5Ctx, Step};
 
@@ -158,7 +510,7 @@ impl LdapClient {
             ClientCtx::new(InitiateFlags::empty(), None, &spn, None).map_err(|e| Error::GssApiError(e.to_string()))?;
 
         let req = self.new_sasl_bind_req("GSSAPI", Some(token.as_ref()));
-        let response = self.do_bind(req).await?;
+        let (_, response) = self.do_bind(req).await?;
 
         let token = match response.server_sasl_creds {
             Some(token) => token,
@@ -179,7 +531,7 @@ impl LdapClient {
         };
 
         let req = self.new_sasl_bind_req("GSSAPI", None);
-        let response = self.do_bind(req).await?;
+        let (_, response) = self.do_bind(req).await?;
 
         if response.server_sasl_creds.is_none() {
             return Err(Error::NoSaslCredentials);
@@ -191,67 +543,143 @@ impl LdapClient {
             .map_err(|e| Error::GssApiError(format!("{}", e)))?;
 
         let req = self.new_sasl_bind_req("GSSAPI", Some(size_msg.as_ref()));
-        self.do_bind(req).await?;
+        let result = self.do_bind(req).await?.0;
 
-        Ok(())
+        *self.bind_credentials.write() = Some(BindCredentials::SaslGssApi {
+            realm: realm.as_ref().to_owned(),
+        });
+
+        Ok(result)
     }
 
     /// Perform the unbind operation. This will instruct the LDAP server to terminate the connection
     pub async fn unbind(&mut self) -> Result<()> {
-        let id = self.new_id();
-
-        let msg = LdapMessage::new(id, ProtocolOp::UnbindRequest(UnbindRequest));
+        let msg = self.new_message(ProtocolOp::UnbindRequest(UnbindRequest), Vec::new());
         self.connection.send(msg).await?;
+        *self.bind_credentials.write() = None;
 
         Ok(())
     }
 
-    /// Send 'whoami' extended request (RFC4532)
-    pub async fn whoami(&mut self) -> Result<Option<String>> {
-        let id = self.new_id();
+    /// Abandon the outstanding operation with a given message id.
+    /// Any pending response stream for this operation ends without an error.
+    pub async fn abandon(&mut self, message_id: u32) -> Result<()> {
+        self.connection.abandon(message_id).await
+    }
 
-        let msg = LdapMessage::new(
-            id,
+    /// Send extended request with a given OID and optional value
+    pub async fn extended<O, V>(&mut self, oid: O, value: Option<V>) -> Result<ExtendedResponse>
+    where
+        O: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.extended_with_options(oid, value, OperationOptions::default())
+            .await
+    }
+
+    /// Send extended request with a given OID, optional value and additional options
+    pub async fn extended_with_options<O, V>(
+        &mut self,
+        oid: O,
+        value: Option<V>,
+        options: OperationOptions,
+    ) -> Result<ExtendedResponse>
+    where
+        O: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let msg = self.new_message(
             ProtocolOp::ExtendedReq(ExtendedRequest {
-                request_name: oid::WHOAMI_OID.into(),
-                request_value: None,
+                request_name: oid.as_ref().to_vec().into(),
+                request_value: value.map(|v| v.as_ref().to_vec().into()),
             }),
+            options.controls,
         );
 
-        let resp = self.connection.send_recv(msg).await?;
+        let timeout = options.timeout.or(self.timeouts.operation);
+        let resp: ExtendedResponse = self.connection.send_recv(msg, timeout).await?.try_into()?;
+        if resp.result_code == ResultCode::Success {
+            Ok(resp)
+        } else {
+            Err(Error::OperationFailed(resp.into()))
+        }
+    }
+
+    /// Send typed extended request
+    pub async fn extended_op<T: ExtendedOperation>(&mut self, op: T) -> Result<T::Response> {
+        self.extended_op_with_options(op, OperationOptions::default()).await
+    }
 
-        match resp.protocol_op {
-            ProtocolOp::ExtendedResp(resp) => {
-                check_result(LdapResult::new(
-                    resp.result_code,
-                    resp.matched_dn,
-                    resp.diagnostic_message,
-                ))?;
-                Ok(resp.response_value.map(|v| String::from_utf8_lossy(&v).into_owned()))
-            }
-            _ => Err(Error::InvalidResponse),
+    /// Send typed extended request with additional options
+    pub async fn extended_op_with_options<T: ExtendedOperation>(
+        &mut self,
+        op: T,
+        options: OperationOptions,
+    ) -> Result<T::Response> {
+        let resp = self.extended_with_options(T::OID, op.value()?, options).await?;
+        T::parse_response(resp)
+    }
+
+    /// Send 'whoami' extended request (RFC4532)
+    pub async fn whoami(&mut self) -> Result<Option<String>> {
+        self.extended_op(WhoAmI).await
+    }
+
+    /// Send password modify extended request (RFC3062). The user identity defaults to the currently bound user.
+    /// Returns the generated password if the new password is not specified.
+    /// Use [LdapClient::extended_op] with [PasswordModify] to send the request with additional options
+    pub async fn password_modify<S, O, N>(
+        &mut self,
+        user_identity: Option<S>,
+        old_password: Option<O>,
+        new_password: Option<N>,
+    ) -> Result<Option<Vec<u8>>>
+    where
+        S: AsRef<str>,
+        O: AsRef<[u8]>,
+        N: AsRef<[u8]>,
+    {
+        let mut op = PasswordModify::new();
+        if let Some(user_identity) = user_identity {
+            op = op.user_identity(user_identity);
+        }
+        if let Some(old_password) = old_password {
+            op = op.old_password(old_password);
         }
+        if let Some(new_password) = new_password {
+            op = op.new_password(new_password);
+        }
+        self.extended_op(op).await
     }
 
     /// Perform search operation without paging. Returns a stream of search entries
     pub async fn search(&mut self, request: SearchRequest) -> Result<SearchEntries> {
-        let id = self.new_id();
-
-        let msg = LdapMessage::new(id, ProtocolOp::SearchRequest(request.into()));
+        let controls = request.controls.clone();
+        let timeout = request.timeout.or(self.timeouts.operation);
+        let msg = self.new_message(ProtocolOp::SearchRequest(request.into()), controls);
         let stream = self.connection.send_recv_stream(msg).await?;
 
-        Ok(SearchEntries {
-            inner: stream,
-            page_control: None,
-            page_finished: Arc::new(AtomicBool::new(false)),
-        })
+        Ok(SearchEntries::new(
+            self.connection.clone(),
+            stream,
+            None,
+            Arc::new(AtomicBool::new(false)),
+            timeout,
+        ))
+    }
+
+    /// Perform search operation without paging, following search continuation references and referrals
+    /// up to a given number of hops. Follow-up connections reuse the TLS options and the last bind credentials
+    pub async fn search_referrals(&mut self, request: SearchRequest, max_hops: u32) -> Result<ReferralEntries> {
+        let items = self.search(request.clone()).await?.items();
+        Ok(ReferralEntries::new(self.clone(), request, items, max_hops))
     }
 
-    /// Perform a search operation without paging and return one result
+    /// Perform a search operation without paging and return one result.
+    /// The rest of the search operation is abandoned after the first entry is received.
     pub async fn search_one(&mut self, request: SearchRequest) -> Result<Option<SearchEntry>> {
-        let entries = self.search(request).await?;
-        let mut attrs = entries.try_collect::<VecDeque<_>>().await?;
-        Ok(attrs.pop_front())
+        let mut entries = self.search(request).await?;
+        entries.try_next().await
     }
 
     /// Perform search operation with paging. Returns a stream of pages
@@ -266,75 +694,197 @@ impl LdapClient {
         }
     }
 
+    /// Perform search operation using the virtual list view control together with the server-side sort control.
+    /// Returns a window of entries and its position in the list
+    pub async fn search_vlv(
+        &mut self,
+        mut request: SearchRequest,
+        sort: ServerSideSortControl,
+        vlv: VirtualListViewControl,
+    ) -> Result<VirtualListView> {
+        request.controls.push(sort.try_into()?);
+        request.controls.push(vlv.try_into()?);
+
+        let mut entries = self.search(request).await?;
+        let items = (&mut entries).try_collect::<Vec<_>>().await;
+        let controls = entries.result().map(|r| r.controls.as_slice()).unwrap_or_default();
+
+        // the response controls describe the failure better than the result code
+        if let Some(sort_result) = SortResultControl::from_controls(controls) {
+            sort_result?.into_result()?;
+        }
+        let response = VirtualListViewResponseControl::from_controls(controls)
+            .map(|r| r.and_then(|r| r.into_result()))
+            .transpose()?;
+        let items = items?;
+        let response = response.ok_or(Error::InvalidResponse)?;
+
+        Ok(VirtualListView {
+            entries: items,
+            target_position: response.target_position,
+            content_count: response.content_count,
+            context_id: response.context_id,
+        })
+    }
+
+    /// Start content synchronization session (RFC4533). Returns a stream of synchronization events
+    pub async fn sync(&mut self, mut request: SearchRequest, control: SyncRequestControl) -> Result<SyncEntries> {
+        request.controls.push(control.try_into()?);
+
+        let controls = request.controls.clone();
+        let msg = self.new_message(ProtocolOp::SearchRequest(request.into()), controls);
+        let stream = self.connection.send_recv_stream(msg).await?;
+
+        Ok(SyncEntries::new(stream, self.connection.clone()))
+    }
+
+    /// Start persistent search. Returns a long-lived stream of changed entries
+    pub async fn persistent_search(
+        &mut self,
+        mut request: SearchRequest,
+        control: PersistentSearchControl,
+    ) -> Result<ChangedEntries> {
+        request.controls.push(control.try_into()?);
+
+        let controls = request.controls.clone();
+        let msg = self.new_message(ProtocolOp::SearchRequest(request.into()), controls);
+        let stream = self.connection.send_recv_stream(msg).await?;
+
+        Ok(ChangedEntries::new(stream, self.connection.clone()))
+    }
+
+    /// Perform Active Directory DirSync search. Returns a stream of changed entries.
+    /// The search is repeated automatically until the server reports no more data
+    pub fn search_dirsync(&mut self, request: SearchRequest, control: DirSyncControl) -> DirSyncEntries {
+        DirSyncEntries::new(self.clone(), request, control)
+    }
+
     /// Perform modify operation
-    pub async fn modify(&mut self, request: ModifyRequest) -> Result<()> {
-        let id = self.new_id();
+    pub async fn modify(&mut self, request: ModifyRequest) -> Result<OperationResult> {
+        let controls = request.controls.clone();
+        let timeout = request.timeout.or(self.timeouts.operation);
+        let msg = self.new_message(ProtocolOp::ModifyRequest(request.into()), controls);
+        let resp = self.connection.send_recv(msg, timeout).await?;
 
-        let msg = LdapMessage::new(id, ProtocolOp::ModifyRequest(request.into()));
-        let resp = self.connection.send_recv(msg).await?;
+        match resp.protocol_op {
+            ProtocolOp::ModifyResponse(op) => check_result(op.0, resp.controls),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    /// Perform modify DN operation, used to rename or move an entry
+    pub async fn modify_dn(&mut self, request: ModifyDnRequest) -> Result<OperationResult> {
+        let controls = request.controls.clone();
+        let timeout = request.timeout.or(self.timeouts.operation);
+        let msg = self.new_message(ProtocolOp::ModDnRequest(request.into()), controls);
+        let resp = self.connection.send_recv(msg, timeout).await?;
 
         match resp.protocol_op {
-            ProtocolOp::ModifyResponse(resp) => {
-                check_result(LdapResult::new(
-                    resp.0.result_code,
-                    resp.0.matched_dn,
-                    resp.0.diagnostic_message,
-                ))?;
-                Ok(())
-            }
+            ProtocolOp::ModDnResponse(op) => check_result(op.0, resp.controls),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    /// Perform compare operation. Returns true if the attribute of a given entry contains the value
+    pub async fn compare<S, A, V>(&mut self, dn: S, attribute: A, value: V) -> Result<bool>
+    where
+        S: AsRef<str>,
+        A: AsRef<str>,
+        V: AsRef<[u8]>,
+    {
+        self.compare_with_options(dn, attribute, value, OperationOptions::default())
+            .await
+    }
+
+    /// Perform compare operation with additional options
+    pub async fn compare_with_options<S, A, V>(
+        &mut self,
+        dn: S,
+        attribute: A,
+        value: V,
+        options: OperationOptions,
+    ) -> Result<bool>
+    where
+        S: AsRef<str>,
+        A: AsRef<str>,
+        V: AsRef<[u8]>,
+    {
+        let msg = self.new_message(
+            ProtocolOp::CompareRequest(CompareRequest {
+                entry: dn.as_ref().to_owned().into(),
+                ava: AttributeValueAssertion::new(attribute.as_ref().into(), value.as_ref().to_vec().into()),
+            }),
+            options.controls,
+        );
+        let timeout = options.timeout.or(self.timeouts.operation);
+        let resp = self.connection.send_recv(msg, timeout).await?;
+
+        match resp.protocol_op {
+            ProtocolOp::CompareResponse(op) => match op.0.result_code {
+                ResultCode::CompareTrue => Ok(true),
+                ResultCode::CompareFalse => Ok(false),
+                _ => Err(Error::OperationFailed(OperationResult::new(op.0, resp.controls).into())),
+            },
             _ => Err(Error::InvalidResponse),
         }
     }
 
     /// Perform add operation
-    pub async fn add<S, I>(&mut self, dn: S, attributes: I) -> Result<()>
+    pub async fn add<S, I>(&mut self, dn: S, attributes: I) -> Result<OperationResult>
     where
         S: AsRef<str>,
         I: IntoIterator<Item = Attribute>,
     {
-        let id = self.new_id();
+        self.add_with_options(dn, attributes, OperationOptions::default()).await
+    }
 
-        let msg = LdapMessage::new(
-            id,
+    /// Perform add operation with additional options
+    pub async fn add_with_options<S, I>(
+        &mut self,
+        dn: S,
+        attributes: I,
+        options: OperationOptions,
+    ) -> Result<OperationResult>
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = Attribute>,
+    {
+        let msg = self.new_message(
             ProtocolOp::AddRequest(rasn_ldap::AddRequest {
                 entry: dn.as_ref().to_owned().into(),
                 attributes: attributes.into_iter().map(Into::into).collect(),
             }),
+            options.controls,
         );
-        let resp = self.connection.send_recv(msg).await?;
+        let timeout = options.timeout.or(self.timeouts.operation);
+        let resp = self.connection.send_recv(msg, timeout).await?;
 
         match resp.protocol_op {
-            ProtocolOp::AddResponse(resp) => {
-                check_result(LdapResult::new(
-                    resp.0.result_code,
-                    resp.0.matched_dn,
-                    resp.0.diagnostic_message,
-                ))?;
-                Ok(())
-            }
+            ProtocolOp::AddResponse(op) => check_result(op.0, resp.controls),
             _ => Err(Error::InvalidResponse),
         }
     }
 
     /// Perform delete operation
-    pub async fn delete<S: AsRef<str>>(&mut self, dn: S) -> Result<()> {
-        let id = self.new_id();
+    pub async fn delete<S: AsRef<str>>(&mut self, dn: S) -> Result<OperationResult> {
+        self.delete_with_options(dn, OperationOptions::default()).await
+    }
 
-        let msg = LdapMessage::new(
-            id,
+    /// Perform delete operation with additional options
+    pub async fn delete_with_options<S: AsRef<str>>(
+        &mut self,
+        dn: S,
+        options: OperationOptions,
+    ) -> Result<OperationResult> {
+        let msg = self.new_message(
             ProtocolOp::DelRequest(rasn_ldap::DelRequest(dn.as_ref().to_owned().into())),
+            options.controls,
         );
-        let resp = self.connection.send_recv(msg).await?;
+        let timeout = options.timeout.or(self.timeouts.operation);
+        let resp = self.connection.send_recv(msg, timeout).await?;
 
         match resp.protocol_op {
-            ProtocolOp::DelResponse(resp) => {
-                check_result(LdapResult::new(
-                    resp.0.result_code,
-                    resp.0.matched_dn,
-                    resp.0.diagnostic_message,
-                ))?;
-                Ok(())
-            }
+            ProtocolOp::DelResponse(op) => check_result(op.0, resp.controls),
             _ => Err(Error::InvalidResponse),
         }
     }
@@ -378,17 +928,19 @@ impl Stream for Pages {
             self.page_finished.store(false, Ordering::SeqCst);
 
             let fut = async move {
-                let id = client.new_id();
-
-                let mut msg = LdapMessage::new(id, ProtocolOp::SearchRequest(request.into()));
-                msg.controls = Some(vec![control_ref.read().clone().with_size(page_size).try_into()?]);
+                let mut controls = request.controls.clone();
+                controls.push(control_ref.read().clone().with_size(page_size).try_into()?);
+                let timeout = request.timeout.or(client.timeouts.operation);
+                let msg = client.new_message(ProtocolOp::SearchRequest(request.into()), controls);
 
                 let stream = client.connection.send_recv_stream(msg).await?;
-                Ok(SearchEntries {
-                    inner: stream,
-                    page_control: Some(control_ref),
+                Ok(SearchEntries::new(
+                    client.connection,
+                    stream,
+                    Some(control_ref),
                     page_finished,
-                })
+                    timeout,
+                ))
             };
             self.inner = Some(Box::pin(fut));
         }
@@ -404,30 +956,120 @@ impl Stream for Pages {
     }
 }
 
-/// A stream of search results
+/// A stream of search results.
+/// Dropping the stream before it is finished abandons the search operation.
 pub struct SearchEntries {
     inner: MessageStream,
+    guard: AbandonGuard,
     page_control: Option<Arc<RwLock<SimplePagedResultsControl>>>,
     page_finished: Arc<AtomicBool>,
+    result: Option<OperationResult>,
+    timeout: Option<Duration>,
+    sleep: Option<Pin<Box<Sleep>>>,
 }
 
 impl SearchEntries {
+    fn new(
+        connection: LdapConnection,
+        inner: MessageStream,
+        page_control: Option<Arc<RwLock<SimplePagedResultsControl>>>,
+        page_finished: Arc<AtomicBool>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            guard: AbandonGuard::new(connection, inner.id()),
+            inner,
+            page_control,
+            page_finished,
+            result: None,
+            timeout,
+            sleep: None,
+        }
+    }
+
+    /// Return the message id of the search operation
+    pub fn message_id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Return the final search result including the response controls.
+    /// It is available after the stream has finished, also if the operation has failed.
+    pub fn result(&self) -> Option<&OperationResult> {
+        self.result.as_ref()
+    }
+
+    /// Convert into a stream which also returns search continuation references
+    pub fn items(self) -> SearchItems {
+        SearchItems(self)
+    }
+
+    fn poll_item(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<SearchItem>>> {
+        if self.guard.is_done() {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Pending => self.poll_timeout(cx),
+            Poll::Ready(None) => {
+                self.guard.set_done();
+                if self.inner.is_abandoned() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Err(Error::ConnectionClosed)))
+                }
+            }
+            Poll::Ready(Some(msg)) => {
+                // the timeout applies to the wait for each response
+                self.sleep = None;
+                self.message_item(msg)
+            }
+        }
+    }
+
+    fn poll_timeout(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<SearchItem>>> {
+        let Some(timeout) = self.timeout else {
+            return Poll::Pending;
+        };
+        let sleep = self.sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+        match sleep.as_mut().poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(()) => {
+                debug!("Search operation {} timed out, abandoning", self.inner.id());
+                self.sleep = None;
+                self.guard.abandon();
+                Poll::Ready(Some(Err(Error::Timeout)))
+            }
+        }
+    }
+
+    fn message_item(self: Pin<&mut Self>, msg: LdapMessage) -> Poll<Option<Result<SearchItem>>> {
+        match msg.protocol_op {
+            ProtocolOp::SearchResEntry(item) => Poll::Ready(Some(Ok(SearchItem::Entry(item.into())))),
+            ProtocolOp::SearchResRef(refs) => Poll::Ready(Some(Ok(SearchItem::Reference(
+                refs.0.into_iter().map(|r| r.0).collect(),
+            )))),
+            ProtocolOp::SearchResDone(done) => self.search_done(msg.controls, done),
+            _ => Poll::Ready(Some(Err(Error::InvalidResponse))),
+        }
+    }
+
     fn search_done(
-        self: Pin<&mut Self>,
+        mut self: Pin<&mut Self>,
         controls: Option<Controls>,
         done: SearchResultDone,
-    ) -> Poll<Option<Result<SearchEntry>>> {
+    ) -> Poll<Option<Result<SearchItem>>> {
+        self.guard.set_done();
         self.page_finished.store(true, Ordering::SeqCst);
 
         if done.0.result_code == ResultCode::Success {
-            if let Some(ref control_ref) = self.page_control {
-                let page_control = controls.and_then(|controls| {
-                    controls
-                        .into_iter()
-                        .find(|c| c.control_type == SimplePagedResultsControl::OID)
-                        .and_then(|c| SimplePagedResultsControl::try_from(c).ok())
-                });
+            let result = OperationResult::new(done.0, controls);
+            let page_control = result
+                .controls
+                .iter()
+                .find(|c| c.control_type == SimplePagedResultsControl::OID)
+                .and_then(|c| SimplePagedResultsControl::try_from(c.clone()).ok());
+            self.result = Some(result);
 
+            if let Some(ref control_ref) = self.page_control {
                 if let Some(page_control) = page_control {
                     *control_ref.write() = page_control;
                     Poll::Ready(None)
@@ -438,7 +1080,10 @@ impl SearchEntries {
                 Poll::Ready(None)
             }
         } else {
-            Poll::Ready(Some(Err(Error::OperationFailed(done.0.into()))))
+            // keep the response controls which may explain the failure
+            let result = OperationResult::new(done.0, controls);
+            self.result = Some(result.clone());
+            Poll::Ready(Some(Err(Error::OperationFailed(result.into()))))
         }
     }
 }
@@ -448,17 +1093,351 @@ impl Stream for SearchEntries {
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         loop {
-            let rc = match Pin::new(&mut self.inner).poll_next(cx) {
+            let rc = match self.as_mut().poll_item(cx) {
                 Poll::Pending => Poll::Pending,
-                Poll::Ready(None) => Poll::Ready(Some(Err(Error::ConnectionClosed))),
-                Poll::Ready(Some(msg)) => match msg.protocol_op {
-                    ProtocolOp::SearchResEntry(item) => Poll::Ready(Some(Ok(item.into()))),
-                    ProtocolOp::SearchResRef(_) => continue,
-                    ProtocolOp::SearchResDone(done) => self.search_done(msg.controls, done),
-                    _ => Poll::Ready(Some(Err(Error::InvalidResponse))),
-                },
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Ready(Some(Ok(SearchItem::Entry(entry)))) => Poll::Ready(Some(Ok(entry))),
+                Poll::Ready(Some(Ok(SearchItem::Reference(_)))) => continue,
+                Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
             };
             return rc;
         }
     }
 }
+
+/// A stream of search results including search continuation references
+pub struct SearchItems(SearchEntries);
+
+impl SearchItems {
+    /// Return the message id of the search operation
+    pub fn message_id(&self) -> u32 {
+        self.0.message_id()
+    }
+
+    /// Return the final search result including the response controls.
+    /// It is available after the stream has finished, also if the operation has failed.
+    pub fn result(&self) -> Option<&OperationResult> {
+        self.0.result()
+    }
+}
+
+impl Stream for SearchItems {
+    type Item = Result<SearchItem>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_item(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use rasn_ldap::SearchResultEntry;
+    use tokio::{
+        net::TcpListener,
+        sync::mpsc::{self, UnboundedReceiver},
+    };
+
+    use super::*;
+    use crate::{
+        controls::SortKey,
+        mock::{MockServer, ldap_result},
+        resolver::SrvRecord,
+    };
+
+    // Server which never answers and passes on the received requests
+    async fn start_server() -> (u16, UnboundedReceiver<LdapMessage>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let server = MockServer::start(move |_, msg| {
+            let _ = tx.send(msg);
+            Vec::new()
+        })
+        .await;
+        (server.port, rx)
+    }
+
+    // Check that the next request abandons the previous one
+    async fn assert_abandoned(requests: &mut UnboundedReceiver<LdapMessage>) {
+        let request = requests.recv().await.unwrap();
+        let abandon = requests.recv().await.unwrap();
+        assert!(matches!(abandon.protocol_op, ProtocolOp::AbandonRequest(id) if id.0 == request.message_id));
+    }
+
+    #[tokio::test]
+    async fn test_operation_timeout() {
+        let (port, mut requests) = start_server().await;
+
+        let client = LdapClient::builder("127.0.0.1")
+            .port(port)
+            .operation_timeout(Duration::from_secs(30))
+            .connect()
+            .await
+            .unwrap();
+
+        let result = client.with_timeout(Duration::from_millis(50)).whoami().await;
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert_abandoned(&mut requests).await;
+    }
+
+    #[tokio::test]
+    async fn test_bind_timeout() {
+        let (port, mut requests) = start_server().await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(port)
+            .operation_timeout(Duration::from_millis(50))
+            .connect()
+            .await
+            .unwrap();
+
+        let result = client.simple_bind("cn=user", "password").await;
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert!(client.is_closed());
+
+        // the bind is not abandoned
+        let request = requests.recv().await.unwrap();
+        assert!(matches!(request.protocol_op, ProtocolOp::BindRequest(_)));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), requests.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_timeout() {
+        let (port, mut requests) = start_server().await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(port)
+            .operation_timeout(Duration::from_secs(30))
+            .connect()
+            .await
+            .unwrap();
+
+        let request = SearchRequest::builder()
+            .filter("(objectClass=*)")
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let mut entries = client.search(request).await.unwrap();
+        assert!(matches!(entries.next().await, Some(Err(Error::Timeout))));
+        assert!(entries.next().await.is_none());
+        assert_abandoned(&mut requests).await;
+    }
+
+    #[tokio::test]
+    async fn test_abandon() {
+        let (port, mut requests) = start_server().await;
+        let mut client = LdapClient::builder("127.0.0.1").port(port).connect().await.unwrap();
+
+        // dropping an unfinished search abandons it
+        let entries = client.search(SearchRequest::root_dse()).await.unwrap();
+        drop(entries);
+        assert_abandoned(&mut requests).await;
+
+        // an explicitly abandoned search ends without an error
+        let mut entries = client.search(SearchRequest::root_dse()).await.unwrap();
+        client.abandon(entries.message_id()).await.unwrap();
+        assert!(entries.next().await.is_none());
+        assert_abandoned(&mut requests).await;
+    }
+
+    #[tokio::test]
+    async fn test_password_modify() {
+        let server = MockServer::start(|_, msg| {
+            let ProtocolOp::ExtendedReq(req) = msg.protocol_op else {
+                return Vec::new();
+            };
+            // the old password only, the new password is generated
+            assert_eq!(req.request_value.unwrap().as_ref(), b"\x30\x05\x81\x03old");
+            let resp = rasn_ldap::ExtendedResponse {
+                result_code: ResultCode::Success,
+                matched_dn: "".into(),
+                diagnostic_message: "".into(),
+                referral: None,
+                response_name: None,
+                response_value: Some(b"\x30\x05\x80\x03gen".as_slice().into()),
+            };
+            vec![LdapMessage::new(msg.message_id, ProtocolOp::ExtendedResp(resp))]
+        })
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let generated = client
+            .password_modify(None::<&str>, Some("old"), None::<&str>)
+            .await
+            .unwrap();
+        assert_eq!(generated.as_deref(), Some(b"gen".as_slice()));
+    }
+
+    struct MockResolver(Vec<SrvRecord>);
+
+    impl Resolver for MockResolver {
+        fn lookup_srv<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<SrvRecord>>> {
+            assert_eq!(name, "_ldap._tcp.example.com");
+            Box::pin(async { Ok(self.0.clone()) })
+        }
+    }
+
+    async fn listen() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let (dead, dead_port) = listen().await;
+        drop(dead);
+        let (first, first_port) = listen().await;
+        let (second, second_port) = listen().await;
+
+        let records = vec![
+            SrvRecord::new(0, 0, dead_port, "127.0.0.1."),
+            SrvRecord::new(1, 0, first_port, "127.0.0.1."),
+        ];
+        let builder = LdapClient::builder_from_domain("example.com")
+            .resolver(MockResolver(records))
+            .server("127.0.0.1", second_port)
+            .failover(FailoverPolicy::RoundRobin);
+
+        // the dead server is skipped, then the next start position is used on each connect
+        builder.clone().connect().await.unwrap();
+        assert!(first.accept().await.is_ok());
+        builder.clone().connect().await.unwrap();
+        assert!(first.accept().await.is_ok());
+        builder.clone().connect().await.unwrap();
+        assert!(second.accept().await.is_ok());
+    }
+
+    #[test]
+    fn test_builder_from_urls() {
+        let builder =
+            LdapClient::builder_from_urls(["ldaps://dc1.example.com", "ldaps://dc2.example.com:1636"]).unwrap();
+        assert_eq!(builder.address, "dc1.example.com");
+        assert_eq!(builder.port, 636);
+        assert_eq!(builder.servers, [("dc2.example.com".to_owned(), 1636)]);
+
+        assert!(LdapClient::builder_from_urls(["ldaps://dc1.example.com", "ldap://dc2.example.com"]).is_err());
+        assert!(LdapClient::builder_from_urls(Vec::<String>::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_operation_controls() {
+        let server = MockServer::start(|_, msg| {
+            // noSuchAttribute in the sort result control
+            let control = Control::new(
+                SortResultControl::OID.into(),
+                false,
+                Some(b"\x30\x03\x0a\x01\x10".as_slice().into()),
+            );
+            let result = ldap_result(ResultCode::UnwillingToPerform);
+            let op = match msg.protocol_op {
+                ProtocolOp::DelRequest(_) => ProtocolOp::DelResponse(rasn_ldap::DelResponse(result)),
+                _ => ProtocolOp::SearchResDone(SearchResultDone(result)),
+            };
+            let mut reply = LdapMessage::new(msg.message_id, op);
+            reply.controls = Some(vec![control]);
+            vec![reply]
+        })
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let Err(Error::OperationFailed(e)) = client.delete("cn=user").await else {
+            panic!("Expected operation error");
+        };
+        assert!(SortResultControl::from_controls(&e.controls).is_some());
+
+        let mut request = SearchRequest::root_dse();
+        request
+            .controls
+            .push(ServerSideSortControl::new([SortKey::new("cn")]).try_into().unwrap());
+        let mut entries = client.search(request).await.unwrap();
+        let Some(Err(Error::OperationFailed(e))) = entries.next().await else {
+            panic!("Expected operation error");
+        };
+        assert_eq!(e.result_code, ResultCode::UnwillingToPerform);
+        let sort_result = SortResultControl::from_controls(&e.controls).unwrap().unwrap();
+        assert_eq!(sort_result.result_code, ResultCode::NoSuchAttribute);
+    }
+
+    #[tokio::test]
+    async fn test_search_vlv() {
+        let server = MockServer::start(|_, msg| {
+            // targetPosition 5, contentCount 100, success
+            let control = Control::new(
+                VirtualListViewResponseControl::OID.into(),
+                false,
+                Some(b"\x30\x09\x02\x01\x05\x02\x01\x64\x0a\x01\x00".as_slice().into()),
+            );
+            let entry = SearchResultEntry::new("cn=user".into(), Vec::new());
+            let done = SearchResultDone(ldap_result(ResultCode::Success));
+            let mut reply = LdapMessage::new(msg.message_id, ProtocolOp::SearchResDone(done));
+            reply.controls = Some(vec![control]);
+            vec![
+                LdapMessage::new(msg.message_id, ProtocolOp::SearchResEntry(entry)),
+                reply,
+            ]
+        })
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let view = client
+            .search_vlv(
+                SearchRequest::root_dse(),
+                ServerSideSortControl::new([SortKey::new("cn")]),
+                VirtualListViewControl::by_offset(0, 10, 5, 0),
+            )
+            .await
+            .unwrap();
+        assert_eq!(view.target_position, 5);
+        assert_eq!(view.content_count, 100);
+        assert_eq!(view.context_id, None);
+        assert_eq!(view.entries.len(), 1);
+        assert_eq!(view.entries[0].dn, "cn=user");
+    }
+
+    #[tokio::test]
+    async fn test_search_vlv_failed() {
+        let server = MockServer::start(|_, msg| {
+            // offsetRangeError in the VLV response control
+            let control = Control::new(
+                VirtualListViewResponseControl::OID.into(),
+                false,
+                Some(b"\x30\x09\x02\x01\x00\x02\x01\x00\x0a\x01\x3d".as_slice().into()),
+            );
+            let done = SearchResultDone(ldap_result(ResultCode::Other));
+            let mut reply = LdapMessage::new(msg.message_id, ProtocolOp::SearchResDone(done));
+            reply.controls = Some(vec![control]);
+            vec![reply]
+        })
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let result = client
+            .search_vlv(
+                SearchRequest::root_dse(),
+                ServerSideSortControl::new([SortKey::new("cn")]),
+                VirtualListViewControl::by_offset(0, 10, 1000, 0),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::VirtualListViewFailed(_))));
+    }
+}
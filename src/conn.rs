@@ -2,8 +2,12 @@ use std::{
     collections::HashMap,
     io,
     pin::Pin,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
     task::{Context, Poll},
+    time::Duration,
 };
 
 use futures::channel::mpsc;
@@ -16,7 +20,7 @@ use crate::{
     channel::{LdapChannel, LdapMessageReceiver, LdapMessageSender},
     error::Error,
     oid,
-    rasn_ldap::{LdapMessage, ProtocolOp},
+    rasn_ldap::{AbandonRequest, LdapMessage, ProtocolOp},
 };
 
 const CHANNEL_SIZE: usize = 1024;
@@ -27,21 +31,22 @@ type RequestMap = Arc<RwLock<HashMap<u32, LdapMessageSender>>>;
 pub struct LdapConnection {
     requests: RequestMap,
     channel_sender: LdapMessageSender,
+    id_counter: Arc<AtomicU32>,
+    closed: Arc<AtomicBool>,
 }
 
 impl LdapConnection {
-    pub async fn connect<A>(address: A, port: u16, tls_options: TlsOptions) -> Result<Self, Error>
-    where
-        A: AsRef<str>,
-    {
-        let (channel_sender, mut channel_receiver) =
-            LdapChannel::for_client(address, port).connect(tls_options).await?;
+    pub async fn connect(channel: LdapChannel, tls_options: TlsOptions) -> Result<Self, Error> {
+        let (channel_sender, mut channel_receiver) = channel.connect(tls_options).await?;
         let connection = Self {
             requests: RequestMap::default(),
             channel_sender,
+            id_counter: Arc::new(AtomicU32::new(2)), // 1 is used by STARTTLS
+            closed: Arc::new(AtomicBool::new(false)),
         };
 
         let requests = connection.requests.clone();
+        let closed = connection.closed.clone();
 
         tokio::spawn(async move {
             while let Some(msg) = channel_receiver.next().await {
@@ -64,37 +69,139 @@ impl LdapConnection {
                 }
             }
             debug!("Connection terminated");
+            closed.store(true, Ordering::SeqCst);
             requests.write().clear();
         });
 
         Ok(connection)
     }
 
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.channel_sender.is_closed()
+    }
+
+    // Close the connection for all clients sharing it
+    pub fn close(&mut self) {
+        self.channel_sender.close_channel();
+    }
+
+    pub fn new_id(&self) -> u32 {
+        self.id_counter.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn new_abandon_msg(&self, id: u32) -> LdapMessage {
+        self.requests.write().remove(&id);
+        LdapMessage::new(self.new_id(), ProtocolOp::AbandonRequest(AbandonRequest(id)))
+    }
+
+    pub async fn abandon(&mut self, id: u32) -> Result<(), Error> {
+        let msg = self.new_abandon_msg(id);
+        self.send(msg).await
+    }
+
+    // Non-blocking version of abandon, used from the Drop implementations
+    pub fn try_abandon(&mut self, id: u32) -> Result<(), Error> {
+        let msg = self.new_abandon_msg(id);
+        Ok(self.channel_sender.try_send(msg).map_err(|e| e.into_send_error())?)
+    }
+
     pub async fn send_recv_stream(&mut self, msg: LdapMessage) -> Result<MessageStream, Error> {
         let id = msg.message_id;
-        self.channel_sender.send(msg).await?;
 
+        // register the receiver before sending so that an early response is not lost
         let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
         self.requests.write().insert(id, tx);
 
-        Ok(MessageStream {
+        let stream = MessageStream {
             id,
             requests: self.requests.clone(),
             receiver: rx,
-        })
+            closed: self.closed.clone(),
+        };
+
+        if self.is_closed() {
+            return Err(Error::ConnectionClosed);
+        }
+
+        self.channel_sender.send(msg).await?;
+
+        Ok(stream)
     }
 
     pub async fn send(&mut self, msg: LdapMessage) -> Result<(), Error> {
         Ok(self.channel_sender.send(msg).await?)
     }
 
-    pub async fn send_recv(&mut self, msg: LdapMessage) -> Result<LdapMessage, Error> {
-        Ok(self
-            .send_recv_stream(msg)
-            .await?
-            .next()
-            .await
-            .ok_or_else(|| io::Error::new(io::ErrorKind::ConnectionReset, "Connection closed"))?)
+    pub async fn send_recv(&mut self, msg: LdapMessage, timeout: Option<Duration>) -> Result<LdapMessage, Error> {
+        let abandonable = is_abandonable(&msg.protocol_op);
+        let mut stream = self.send_recv_stream(msg).await?;
+        let response = match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, stream.next()).await {
+                Ok(response) => response,
+                Err(_) if abandonable => {
+                    debug!("Operation {} timed out, abandoning", stream.id());
+                    // the timeout is reported even if the abandon request cannot be sent
+                    let _ = self.abandon(stream.id()).await;
+                    return Err(Error::Timeout);
+                }
+                Err(_) => {
+                    // the connection state is unknown after a bind or STARTTLS timeout
+                    debug!("Operation {} timed out, closing the connection", stream.id());
+                    self.close();
+                    return Err(Error::Timeout);
+                }
+            },
+            None => stream.next().await,
+        };
+        Ok(response.ok_or_else(|| io::Error::new(io::ErrorKind::ConnectionReset, "Connection closed"))?)
+    }
+}
+
+// Bind and STARTTLS cannot be abandoned (RFC4511 4.11)
+fn is_abandonable(op: &ProtocolOp) -> bool {
+    match op {
+        ProtocolOp::BindRequest(_) => false,
+        ProtocolOp::ExtendedReq(req) => req.request_name.as_ref() != oid::STARTTLS_OID,
+        _ => true,
+    }
+}
+
+// Abandons the operation on drop unless it is finished
+pub struct AbandonGuard {
+    connection: LdapConnection,
+    id: u32,
+    done: bool,
+}
+
+impl AbandonGuard {
+    pub fn new(connection: LdapConnection, id: u32) -> Self {
+        Self {
+            connection,
+            id,
+            done: false,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn set_done(&mut self) {
+        self.done = true;
+    }
+
+    // Abandon the operation now if it is not finished
+    pub fn abandon(&mut self) {
+        if !self.done {
+            let _ = self.connection.try_abandon(self.id);
+            self.done = true;
+        }
+    }
+}
+
+impl Drop for AbandonGuard {
+    fn drop(&mut self) {
+        self.abandon();
     }
 }
 
@@ -102,6 +209,19 @@ pub struct MessageStream {
     id: u32,
     requests: RequestMap,
     receiver: LdapMessageReceiver,
+    closed: Arc<AtomicBool>,
+}
+
+impl MessageStream {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    // Whether the finished stream was terminated by abandoning the operation rather than by a lost connection.
+    // The connection is marked closed before the pending streams are terminated
+    pub fn is_abandoned(&self) -> bool {
+        !self.closed.load(Ordering::SeqCst)
+    }
 }
 
 impl Stream for MessageStream {
@@ -2,11 +2,25 @@
 
 use std::convert::TryFrom;
 
-use rasn::{Decode, Decoder, Encode, ber, types::*};
-use rasn_ldap::Control;
+use rasn::{Decode, Decoder, Encode, Encoder, ber, types::*};
+use rasn_ldap::{Control, ResultCode};
 
 use crate::error::Error;
 
+/// Response control which is returned by the server in the response message
+pub trait ResponseControl: TryFrom<Control, Error = Error> {
+    /// Control OID
+    const OID: &'static [u8];
+
+    /// Find and decode the control in a given list of response controls
+    fn from_controls(controls: &[Control]) -> Option<Result<Self, Error>> {
+        controls
+            .iter()
+            .find(|c| c.control_type == Self::OID)
+            .map(|c| Self::try_from(c.clone()))
+    }
+}
+
 /// Simple paged result control, OID 1.2.840.113556.1.4.319
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct SimplePagedResultsControl {
@@ -88,3 +102,950 @@ impl TryFrom<Control> for SimplePagedResultsControl {
         })
     }
 }
+
+/// Sort key for the server-side sort control
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct SortKey {
+    attribute: String,
+    ordering_rule: Option<String>,
+    reverse: bool,
+}
+
+impl SortKey {
+    /// Create a sort key for a given attribute
+    pub fn new<S: AsRef<str>>(attribute: S) -> Self {
+        Self {
+            attribute: attribute.as_ref().to_owned(),
+            ordering_rule: None,
+            reverse: false,
+        }
+    }
+
+    /// Set the ordering rule OID to use for this key
+    pub fn ordering_rule<S: AsRef<str>>(mut self, ordering_rule: S) -> Self {
+        self.ordering_rule = Some(ordering_rule.as_ref().to_owned());
+        self
+    }
+
+    /// Set a flag indicating a reverse sort order
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+}
+
+/// Server-side sort request control (RFC2891), OID 1.2.840.113556.1.4.473
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct ServerSideSortControl {
+    keys: Vec<SortKey>,
+    critical: bool,
+}
+
+impl ServerSideSortControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::SERVER_SIDE_SORT_CONTROL_OID;
+
+    /// Create a sort control with a given list of sort keys
+    pub fn new<I>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = SortKey>,
+    {
+        Self {
+            keys: keys.into_iter().collect(),
+            critical: false,
+        }
+    }
+
+    /// Set control criticality, default is false.
+    /// If the control is critical the server will fail the search when it cannot sort the results.
+    pub fn critical(mut self, critical: bool) -> Self {
+        self.critical = critical;
+        self
+    }
+
+    /// Return the sort keys
+    pub fn keys(&self) -> &[SortKey] {
+        &self.keys
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealSortKey {
+    attribute_type: OctetString,
+    #[rasn(tag(0))]
+    ordering_rule: Option<OctetString>,
+    #[rasn(tag(1), default)]
+    reverse_order: bool,
+}
+
+impl TryFrom<ServerSideSortControl> for Control {
+    type Error = Error;
+
+    fn try_from(control: ServerSideSortControl) -> Result<Self, Self::Error> {
+        let value = control
+            .keys
+            .into_iter()
+            .map(|key| RealSortKey {
+                attribute_type: key.attribute.into_bytes().into(),
+                ordering_rule: key.ordering_rule.map(|r| r.into_bytes().into()),
+                reverse_order: key.reverse,
+            })
+            .collect::<Vec<_>>();
+        Ok(Control::new(
+            ServerSideSortControl::OID.into(),
+            control.critical,
+            Some(ber::encode(&value)?.into()),
+        ))
+    }
+}
+
+/// Server-side sort response control (RFC2891), OID 1.2.840.113556.1.4.474
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct SortResultControl {
+    /// Sort result code
+    pub result_code: ResultCode,
+    /// The attribute which caused the failure
+    pub attribute_type: Option<String>,
+}
+
+impl ResponseControl for SortResultControl {
+    const OID: &'static [u8] = SortResultControl::OID;
+}
+
+impl SortResultControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::SORT_RESULT_CONTROL_OID;
+
+    /// Convert the sort result into an error if the server could not sort the results
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.result_code == ResultCode::Success {
+            Ok(())
+        } else {
+            Err(Error::SortFailed(self))
+        }
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealSortResult {
+    sort_result: ResultCode,
+    #[rasn(tag(0))]
+    attribute_type: Option<OctetString>,
+}
+
+impl TryFrom<Control> for SortResultControl {
+    type Error = Error;
+
+    fn try_from(value: Control) -> Result<Self, Self::Error> {
+        let value = ber::decode::<RealSortResult>(value.control_value.as_deref().unwrap_or(b""))?;
+
+        Ok(SortResultControl {
+            result_code: value.sort_result,
+            attribute_type: value.attribute_type.map(|a| String::from_utf8_lossy(&a).into_owned()),
+        })
+    }
+}
+
+/// Virtual list view target
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum VirtualListViewTarget {
+    /// Target entry is specified by its offset and the client estimate of the content count
+    ByOffset { offset: u32, content_count: u32 },
+    /// Target entry is the first one which is greater than or equal to the value of the primary sort key
+    GreaterThanOrEqual(Vec<u8>),
+}
+
+/// Virtual list view request control (draft-ietf-ldapext-ldapv3-vlv), OID 2.16.840.1.113730.3.4.9.
+/// Must be used together with the server-side sort control.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct VirtualListViewControl {
+    before_count: u32,
+    after_count: u32,
+    target: VirtualListViewTarget,
+    context_id: Option<Vec<u8>>,
+}
+
+impl VirtualListViewControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::VLV_REQUEST_CONTROL_OID;
+
+    /// Request a window of entries around a given offset. The offset is 1-based,
+    /// content count is the client estimate of the list size or 0 if not known
+    pub fn by_offset(before_count: u32, after_count: u32, offset: u32, content_count: u32) -> Self {
+        Self {
+            before_count,
+            after_count,
+            target: VirtualListViewTarget::ByOffset { offset, content_count },
+            context_id: None,
+        }
+    }
+
+    /// Request a window of entries around the first entry with the primary sort key value
+    /// greater than or equal to a given value
+    pub fn by_value<V: AsRef<[u8]>>(before_count: u32, after_count: u32, value: V) -> Self {
+        Self {
+            before_count,
+            after_count,
+            target: VirtualListViewTarget::GreaterThanOrEqual(value.as_ref().to_vec()),
+            context_id: None,
+        }
+    }
+
+    /// Set the context id returned by the server in the previous response
+    pub fn context_id<V: AsRef<[u8]>>(mut self, context_id: V) -> Self {
+        self.context_id = Some(context_id.as_ref().to_vec());
+        self
+    }
+
+    /// Return the target of the request
+    pub fn target(&self) -> &VirtualListViewTarget {
+        &self.target
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealVlvByOffset {
+    offset: u32,
+    content_count: u32,
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[rasn(choice)]
+enum RealVlvTarget {
+    #[rasn(tag(0))]
+    ByOffset(RealVlvByOffset),
+    #[rasn(tag(1))]
+    GreaterThanOrEqual(OctetString),
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealVlvRequest {
+    before_count: u32,
+    after_count: u32,
+    target: RealVlvTarget,
+    context_id: Option<OctetString>,
+}
+
+impl TryFrom<VirtualListViewControl> for Control {
+    type Error = Error;
+
+    fn try_from(control: VirtualListViewControl) -> Result<Self, Self::Error> {
+        let value = RealVlvRequest {
+            before_count: control.before_count,
+            after_count: control.after_count,
+            target: match control.target {
+                VirtualListViewTarget::ByOffset { offset, content_count } => {
+                    RealVlvTarget::ByOffset(RealVlvByOffset { offset, content_count })
+                }
+                VirtualListViewTarget::GreaterThanOrEqual(value) => RealVlvTarget::GreaterThanOrEqual(value.into()),
+            },
+            context_id: control.context_id.map(Into::into),
+        };
+        Ok(Control::new(
+            VirtualListViewControl::OID.into(),
+            true,
+            Some(ber::encode(&value)?.into()),
+        ))
+    }
+}
+
+/// Virtual list view result code
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum VirtualListViewResult {
+    Success,
+    OperationsError,
+    ProtocolError,
+    TimeLimitExceeded,
+    AdminLimitExceeded,
+    InappropriateMatching,
+    InsufficientAccessRights,
+    UnwillingToPerform,
+    SortControlMissing,
+    OffsetRangeError,
+    /// Any other result code, including the vendor-specific ones
+    Other(u32),
+}
+
+impl From<u32> for VirtualListViewResult {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Success,
+            1 => Self::OperationsError,
+            2 => Self::ProtocolError,
+            3 => Self::TimeLimitExceeded,
+            11 => Self::AdminLimitExceeded,
+            18 => Self::InappropriateMatching,
+            50 => Self::InsufficientAccessRights,
+            53 => Self::UnwillingToPerform,
+            60 => Self::SortControlMissing,
+            61 => Self::OffsetRangeError,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// Virtual list view response control (draft-ietf-ldapext-ldapv3-vlv), OID 2.16.840.1.113730.3.4.10
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct VirtualListViewResponseControl {
+    /// Position of the target entry in the list, 1-based
+    pub target_position: u32,
+    /// Server estimate of the list size
+    pub content_count: u32,
+    /// Result code
+    pub result: VirtualListViewResult,
+    /// Context id to pass in the next request
+    pub context_id: Option<Vec<u8>>,
+}
+
+impl ResponseControl for VirtualListViewResponseControl {
+    const OID: &'static [u8] = VirtualListViewResponseControl::OID;
+}
+
+impl VirtualListViewResponseControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::VLV_RESPONSE_CONTROL_OID;
+
+    /// Convert the response into an error if the server could not process the virtual list view request
+    pub fn into_result(self) -> Result<Self, Error> {
+        if self.result == VirtualListViewResult::Success {
+            Ok(self)
+        } else {
+            Err(Error::VirtualListViewFailed(self))
+        }
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealVlvResponse {
+    target_position: u32,
+    content_count: u32,
+    #[rasn(tag(universal, 10))]
+    virtual_list_view_result: u32,
+    context_id: Option<OctetString>,
+}
+
+impl TryFrom<Control> for VirtualListViewResponseControl {
+    type Error = Error;
+
+    fn try_from(value: Control) -> Result<Self, Self::Error> {
+        let value = ber::decode::<RealVlvResponse>(value.control_value.as_deref().unwrap_or(b""))?;
+
+        Ok(VirtualListViewResponseControl {
+            target_position: value.target_position,
+            content_count: value.content_count,
+            result: value.virtual_list_view_result.into(),
+            context_id: value.context_id.map(|c| c.to_vec()),
+        })
+    }
+}
+
+/// Content synchronization mode
+#[derive(AsnType, Encode, Decode, Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[rasn(enumerated)]
+pub enum SyncRequestMode {
+    /// Return the changes since the cookie and finish the search
+    RefreshOnly = 1,
+    /// Return the changes since the cookie and keep sending new changes
+    RefreshAndPersist = 3,
+}
+
+/// Content synchronization request control (RFC4533), OID 1.3.6.1.4.1.4203.1.9.1.1
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct SyncRequestControl {
+    mode: SyncRequestMode,
+    cookie: Option<Vec<u8>>,
+    reload_hint: bool,
+}
+
+impl SyncRequestControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::SYNC_REQUEST_CONTROL_OID;
+
+    /// Create a sync request control with a given mode
+    pub fn new(mode: SyncRequestMode) -> Self {
+        Self {
+            mode,
+            cookie: None,
+            reload_hint: false,
+        }
+    }
+
+    /// Set the cookie returned by the previous synchronization session
+    pub fn cookie<V: AsRef<[u8]>>(mut self, cookie: V) -> Self {
+        self.cookie = Some(cookie.as_ref().to_vec());
+        self
+    }
+
+    /// Set a flag indicating that the client wants a full reload if the cookie cannot be used
+    pub fn reload_hint(mut self, reload_hint: bool) -> Self {
+        self.reload_hint = reload_hint;
+        self
+    }
+
+    /// Return the synchronization mode
+    pub fn mode(&self) -> SyncRequestMode {
+        self.mode
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealSyncRequest {
+    mode: SyncRequestMode,
+    cookie: Option<OctetString>,
+    #[rasn(default)]
+    reload_hint: bool,
+}
+
+impl TryFrom<SyncRequestControl> for Control {
+    type Error = Error;
+
+    fn try_from(control: SyncRequestControl) -> Result<Self, Self::Error> {
+        let value = RealSyncRequest {
+            mode: control.mode,
+            cookie: control.cookie.map(Into::into),
+            reload_hint: control.reload_hint,
+        };
+        Ok(Control::new(
+            SyncRequestControl::OID.into(),
+            true,
+            Some(ber::encode(&value)?.into()),
+        ))
+    }
+}
+
+/// Synchronization state of the entry
+#[derive(AsnType, Encode, Decode, Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[rasn(enumerated)]
+pub enum SyncState {
+    Present = 0,
+    Add = 1,
+    Modify = 2,
+    Delete = 3,
+}
+
+/// Content synchronization state control (RFC4533), OID 1.3.6.1.4.1.4203.1.9.1.2.
+/// It is attached by the server to every returned entry
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct SyncStateControl {
+    /// Entry state
+    pub state: SyncState,
+    /// Entry UUID
+    pub entry_uuid: Vec<u8>,
+    /// Synchronization cookie
+    pub cookie: Option<Vec<u8>>,
+}
+
+impl SyncStateControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::SYNC_STATE_CONTROL_OID;
+}
+
+impl ResponseControl for SyncStateControl {
+    const OID: &'static [u8] = SyncStateControl::OID;
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealSyncState {
+    state: SyncState,
+    entry_uuid: OctetString,
+    cookie: Option<OctetString>,
+}
+
+impl TryFrom<Control> for SyncStateControl {
+    type Error = Error;
+
+    fn try_from(value: Control) -> Result<Self, Self::Error> {
+        let value = ber::decode::<RealSyncState>(value.control_value.as_deref().unwrap_or(b""))?;
+
+        Ok(SyncStateControl {
+            state: value.state,
+            entry_uuid: value.entry_uuid.to_vec(),
+            cookie: value.cookie.map(|c| c.to_vec()),
+        })
+    }
+}
+
+/// Content synchronization done control (RFC4533), OID 1.3.6.1.4.1.4203.1.9.1.3.
+/// It is attached by the server to the search result at the end of the synchronization session
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct SyncDoneControl {
+    /// Synchronization cookie
+    pub cookie: Option<Vec<u8>>,
+    /// True if the refresh phase used delete mode, false if present mode was used
+    pub refresh_deletes: bool,
+}
+
+impl SyncDoneControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::SYNC_DONE_CONTROL_OID;
+}
+
+impl ResponseControl for SyncDoneControl {
+    const OID: &'static [u8] = SyncDoneControl::OID;
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealSyncDone {
+    cookie: Option<OctetString>,
+    #[rasn(default)]
+    refresh_deletes: bool,
+}
+
+impl TryFrom<Control> for SyncDoneControl {
+    type Error = Error;
+
+    fn try_from(value: Control) -> Result<Self, Self::Error> {
+        let value = ber::decode::<RealSyncDone>(value.control_value.as_deref().unwrap_or(b""))?;
+
+        Ok(SyncDoneControl {
+            cookie: value.cookie.map(|c| c.to_vec()),
+            refresh_deletes: value.refresh_deletes,
+        })
+    }
+}
+
+/// Change type reported by the persistent search
+#[derive(AsnType, Encode, Decode, Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[rasn(enumerated)]
+pub enum ChangeType {
+    Add = 1,
+    Delete = 2,
+    Modify = 4,
+    ModDn = 8,
+}
+
+/// Persistent search request control (draft-ietf-ldapext-psearch), OID 2.16.840.1.113730.3.4.3
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct PersistentSearchControl {
+    change_types: u32,
+    changes_only: bool,
+    return_ecs: bool,
+}
+
+impl Default for PersistentSearchControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PersistentSearchControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::PERSISTENT_SEARCH_CONTROL_OID;
+
+    /// Create a persistent search control for all change types which returns only changed entries
+    /// together with the entry change notification controls
+    pub fn new() -> Self {
+        Self {
+            change_types: [
+                ChangeType::Add,
+                ChangeType::Delete,
+                ChangeType::Modify,
+                ChangeType::ModDn,
+            ]
+            .into_iter()
+            .fold(0, |acc, t| acc | t as u32),
+            changes_only: true,
+            return_ecs: true,
+        }
+    }
+
+    /// Set the change types to be notified about
+    pub fn change_types<I>(mut self, change_types: I) -> Self
+    where
+        I: IntoIterator<Item = ChangeType>,
+    {
+        self.change_types = change_types.into_iter().fold(0, |acc, t| acc | t as u32);
+        self
+    }
+
+    /// Set a flag indicating that only changed entries should be returned, default is true.
+    /// If false, the server returns all existing entries first
+    pub fn changes_only(mut self, changes_only: bool) -> Self {
+        self.changes_only = changes_only;
+        self
+    }
+
+    /// Set a flag indicating that entry change notification controls should be returned, default is true
+    pub fn return_ecs(mut self, return_ecs: bool) -> Self {
+        self.return_ecs = return_ecs;
+        self
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealPersistentSearch {
+    change_types: u32,
+    changes_only: bool,
+    return_ecs: bool,
+}
+
+impl TryFrom<PersistentSearchControl> for Control {
+    type Error = Error;
+
+    fn try_from(control: PersistentSearchControl) -> Result<Self, Self::Error> {
+        let value = RealPersistentSearch {
+            change_types: control.change_types,
+            changes_only: control.changes_only,
+            return_ecs: control.return_ecs,
+        };
+        Ok(Control::new(
+            PersistentSearchControl::OID.into(),
+            true,
+            Some(ber::encode(&value)?.into()),
+        ))
+    }
+}
+
+/// Entry change notification response control (draft-ietf-ldapext-psearch), OID 2.16.840.1.113730.3.4.7
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct EntryChangeNotificationControl {
+    /// Change type
+    pub change_type: ChangeType,
+    /// Previous DN of the entry, only present for the ModDn change type
+    pub previous_dn: Option<String>,
+    /// Change number
+    pub change_number: Option<i64>,
+}
+
+impl EntryChangeNotificationControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::ENTRY_CHANGE_NOTIFICATION_CONTROL_OID;
+}
+
+impl ResponseControl for EntryChangeNotificationControl {
+    const OID: &'static [u8] = EntryChangeNotificationControl::OID;
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealEntryChangeNotification {
+    change_type: ChangeType,
+    previous_dn: Option<OctetString>,
+    change_number: Option<i64>,
+}
+
+impl TryFrom<Control> for EntryChangeNotificationControl {
+    type Error = Error;
+
+    fn try_from(value: Control) -> Result<Self, Self::Error> {
+        let value = ber::decode::<RealEntryChangeNotification>(value.control_value.as_deref().unwrap_or(b""))?;
+
+        Ok(EntryChangeNotificationControl {
+            change_type: value.change_type,
+            previous_dn: value.previous_dn.map(|dn| String::from_utf8_lossy(&dn).into_owned()),
+            change_number: value.change_number,
+        })
+    }
+}
+
+/// Active Directory DirSync request control, OID 1.2.840.113556.1.4.841
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct DirSyncControl {
+    flags: u32,
+    max_bytes: u32,
+    pub(crate) cookie: Vec<u8>,
+}
+
+impl Default for DirSyncControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirSyncControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::DIRSYNC_CONTROL_OID;
+
+    /// Return only the data the caller has access to, does not require replication rights
+    pub const OBJECT_SECURITY: u32 = 0x0000_0001;
+
+    /// Return parent objects before their children
+    pub const ANCESTORS_FIRST_ORDER: u32 = 0x0000_0800;
+
+    /// Do not return secret data
+    pub const PUBLIC_DATA_ONLY: u32 = 0x0000_2000;
+
+    /// Return only the changed values of multi-valued attributes
+    pub const INCREMENTAL_VALUES: u32 = 0x8000_0000;
+
+    /// Create a DirSync control without flags, cookie and with the default size limit
+    pub fn new() -> Self {
+        Self {
+            flags: 0,
+            max_bytes: 0,
+            cookie: Vec::new(),
+        }
+    }
+
+    /// Set the request flags
+    pub fn flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the maximum amount of data returned by the server in one round, 0 means the server default
+    pub fn max_bytes(mut self, max_bytes: u32) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Set the cookie returned by the previous DirSync session
+    pub fn cookie<V: AsRef<[u8]>>(mut self, cookie: V) -> Self {
+        self.cookie = cookie.as_ref().to_vec();
+        self
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+struct RealDirSyncValue {
+    flags: i32,
+    max_bytes: i32,
+    cookie: OctetString,
+}
+
+impl TryFrom<DirSyncControl> for Control {
+    type Error = Error;
+
+    fn try_from(control: DirSyncControl) -> Result<Self, Self::Error> {
+        let value = RealDirSyncValue {
+            flags: control.flags as i32,
+            max_bytes: control.max_bytes as i32,
+            cookie: control.cookie.into(),
+        };
+        Ok(Control::new(
+            DirSyncControl::OID.into(),
+            true,
+            Some(ber::encode(&value)?.into()),
+        ))
+    }
+}
+
+/// Active Directory DirSync response control, OID 1.2.840.113556.1.4.841
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct DirSyncResponseControl {
+    /// True if more data is available
+    pub more_results: bool,
+    /// Cookie to pass in the next request
+    pub cookie: Vec<u8>,
+}
+
+impl DirSyncResponseControl {
+    /// Control OID
+    pub const OID: &'static [u8] = crate::oid::DIRSYNC_CONTROL_OID;
+}
+
+impl ResponseControl for DirSyncResponseControl {
+    const OID: &'static [u8] = DirSyncResponseControl::OID;
+}
+
+impl TryFrom<Control> for DirSyncResponseControl {
+    type Error = Error;
+
+    fn try_from(value: Control) -> Result<Self, Self::Error> {
+        let value = ber::decode::<RealDirSyncValue>(value.control_value.as_deref().unwrap_or(b""))?;
+
+        Ok(DirSyncResponseControl {
+            more_results: value.flags != 0,
+            cookie: value.cookie.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_control() {
+        let control: Control = ServerSideSortControl::new([
+            SortKey::new("sn"),
+            SortKey::new("cn").ordering_rule("2.5.13.3").reverse(true),
+        ])
+        .try_into()
+        .unwrap();
+
+        assert_eq!(control.control_type, ServerSideSortControl::OID);
+        assert!(!control.criticality);
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x19\x30\x04\x04\x02sn\x30\x11\x04\x02cn\x80\x082.5.13.3\x81\x01\xff"
+        );
+    }
+
+    #[test]
+    fn test_sort_result_control() {
+        let control = Control::new(
+            SortResultControl::OID.into(),
+            false,
+            Some(b"\x30\x07\x0a\x01\x10\x80\x02sn".as_slice().into()),
+        );
+        let result = SortResultControl::from_controls(&[control]).unwrap().unwrap();
+        assert_eq!(result.result_code, ResultCode::NoSuchAttribute);
+        assert_eq!(result.attribute_type.as_deref(), Some("sn"));
+        assert!(matches!(result.into_result(), Err(Error::SortFailed(_))));
+
+        let control = Control::new(
+            SortResultControl::OID.into(),
+            false,
+            Some(b"\x30\x03\x0a\x01\x00".as_slice().into()),
+        );
+        assert!(SortResultControl::try_from(control).unwrap().into_result().is_ok());
+    }
+
+    #[test]
+    fn test_vlv_control() {
+        let control: Control = VirtualListViewControl::by_offset(1, 2, 3, 0).try_into().unwrap();
+        assert_eq!(control.control_type, VirtualListViewControl::OID);
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x0e\x02\x01\x01\x02\x01\x02\xa0\x06\x02\x01\x03\x02\x01\x00"
+        );
+
+        let control: Control = VirtualListViewControl::by_value(0, 5, "Sm")
+            .context_id("ctx")
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x0f\x02\x01\x00\x02\x01\x05\x81\x02Sm\x04\x03ctx"
+        );
+    }
+
+    #[test]
+    fn test_vlv_response_control() {
+        let control = Control::new(
+            VirtualListViewResponseControl::OID.into(),
+            false,
+            Some(
+                b"\x30\x0e\x02\x01\x03\x02\x02\x01\x00\x0a\x01\x00\x04\x02id"
+                    .as_slice()
+                    .into(),
+            ),
+        );
+        let response = VirtualListViewResponseControl::from_controls(&[control])
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.target_position, 3);
+        assert_eq!(response.content_count, 256);
+        assert_eq!(response.result, VirtualListViewResult::Success);
+        assert_eq!(response.context_id.as_deref(), Some(b"id".as_slice()));
+
+        let control = Control::new(
+            VirtualListViewResponseControl::OID.into(),
+            false,
+            Some(b"\x30\x09\x02\x01\x00\x02\x01\x00\x0a\x01\x3d".as_slice().into()),
+        );
+        let response = VirtualListViewResponseControl::try_from(control).unwrap();
+        assert_eq!(response.result, VirtualListViewResult::OffsetRangeError);
+        assert!(matches!(response.into_result(), Err(Error::VirtualListViewFailed(_))));
+
+        let control = Control::new(
+            VirtualListViewResponseControl::OID.into(),
+            false,
+            Some(b"\x30\x09\x02\x01\x00\x02\x01\x00\x0a\x01\x7b".as_slice().into()),
+        );
+        let response = VirtualListViewResponseControl::try_from(control).unwrap();
+        assert_eq!(response.result, VirtualListViewResult::Other(123));
+    }
+
+    #[test]
+    fn test_sync_request_control() {
+        let control: Control = SyncRequestControl::new(SyncRequestMode::RefreshAndPersist)
+            .cookie("abc")
+            .try_into()
+            .unwrap();
+        assert_eq!(control.control_type, SyncRequestControl::OID);
+        assert!(control.criticality);
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x08\x0a\x01\x03\x04\x03abc"
+        );
+    }
+
+    #[test]
+    fn test_sync_state_control() {
+        let control = Control::new(
+            SyncStateControl::OID.into(),
+            false,
+            Some(
+                b"\x30\x1a\x0a\x01\x02\x04\x100123456789abcdef\x04\x03abc"
+                    .as_slice()
+                    .into(),
+            ),
+        );
+        let state = SyncStateControl::from_controls(&[control]).unwrap().unwrap();
+        assert_eq!(state.state, SyncState::Modify);
+        assert_eq!(state.entry_uuid, b"0123456789abcdef");
+        assert_eq!(state.cookie.as_deref(), Some(b"abc".as_slice()));
+    }
+
+    #[test]
+    fn test_sync_done_control() {
+        let control = Control::new(
+            SyncDoneControl::OID.into(),
+            false,
+            Some(b"\x30\x08\x04\x03abc\x01\x01\xff".as_slice().into()),
+        );
+        let done = SyncDoneControl::try_from(control).unwrap();
+        assert_eq!(done.cookie.as_deref(), Some(b"abc".as_slice()));
+        assert!(done.refresh_deletes);
+    }
+
+    #[test]
+    fn test_persistent_search_control() {
+        let control: Control = PersistentSearchControl::new().try_into().unwrap();
+        assert_eq!(control.control_type, PersistentSearchControl::OID);
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x09\x02\x01\x0f\x01\x01\xff\x01\x01\xff"
+        );
+
+        let control: Control = PersistentSearchControl::new()
+            .change_types([ChangeType::Add, ChangeType::ModDn])
+            .changes_only(false)
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x09\x02\x01\x09\x01\x01\x00\x01\x01\xff"
+        );
+    }
+
+    #[test]
+    fn test_entry_change_notification_control() {
+        let control = Control::new(
+            EntryChangeNotificationControl::OID.into(),
+            false,
+            Some(b"\x30\x0d\x0a\x01\x08\x04\x05cn=ab\x02\x01\x2a".as_slice().into()),
+        );
+        let ecn = EntryChangeNotificationControl::from_controls(&[control])
+            .unwrap()
+            .unwrap();
+        assert_eq!(ecn.change_type, ChangeType::ModDn);
+        assert_eq!(ecn.previous_dn.as_deref(), Some("cn=ab"));
+        assert_eq!(ecn.change_number, Some(42));
+    }
+
+    #[test]
+    fn test_dirsync_control() {
+        let control: Control = DirSyncControl::new()
+            .flags(DirSyncControl::OBJECT_SECURITY | DirSyncControl::INCREMENTAL_VALUES)
+            .cookie("abc")
+            .try_into()
+            .unwrap();
+        assert_eq!(control.control_type, DirSyncControl::OID);
+        assert!(control.criticality);
+        assert_eq!(
+            control.control_value.as_deref().unwrap(),
+            b"\x30\x0e\x02\x04\x80\x00\x00\x01\x02\x01\x00\x04\x03abc"
+        );
+
+        let control = Control::new(
+            DirSyncResponseControl::OID.into(),
+            false,
+            Some(b"\x30\x0b\x02\x01\x01\x02\x01\x00\x04\x03xyz".as_slice().into()),
+        );
+        let response = DirSyncResponseControl::from_controls(&[control]).unwrap().unwrap();
+        assert!(response.more_results);
+        assert_eq!(response.cookie, b"xyz");
+    }
+}
@@ -0,0 +1,176 @@
+//! Active Directory DirSync
+
+use std::{
+    convert::TryInto,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Future, Stream, future::BoxFuture};
+
+use crate::{
+    LdapClient, SearchEntries, SearchEntry,
+    controls::{DirSyncControl, DirSyncResponseControl, ResponseControl},
+    error::Error,
+    request::SearchRequest,
+};
+
+#[allow(clippy::large_enum_variant)]
+enum State {
+    Idle,
+    Searching(BoxFuture<'static, Result<SearchEntries, Error>>),
+    Streaming(SearchEntries),
+    Finished,
+}
+
+/// A stream of entries returned by the DirSync search.
+/// The search is repeated with the updated cookie while the server indicates that more data is available.
+pub struct DirSyncEntries {
+    client: LdapClient,
+    request: SearchRequest,
+    control: DirSyncControl,
+    state: State,
+}
+
+impl DirSyncEntries {
+    pub(crate) fn new(client: LdapClient, request: SearchRequest, control: DirSyncControl) -> Self {
+        Self {
+            client,
+            request,
+            control,
+            state: State::Idle,
+        }
+    }
+
+    /// Return the most recent cookie. After the stream is finished it can be persisted
+    /// and used to retrieve the subsequent changes
+    pub fn cookie(&self) -> &[u8] {
+        &self.control.cookie
+    }
+
+    fn round_done(&mut self, entries: &SearchEntries) -> Result<(), Error> {
+        let response = entries
+            .result()
+            .and_then(|r| DirSyncResponseControl::from_controls(&r.controls))
+            .ok_or(Error::InvalidResponse)??;
+
+        self.control.cookie = response.cookie;
+        self.state = if response.more_results {
+            State::Idle
+        } else {
+            State::Finished
+        };
+        Ok(())
+    }
+}
+
+impl Stream for DirSyncEntries {
+    type Item = Result<SearchEntry, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.state {
+                State::Finished => return Poll::Ready(None),
+                State::Idle => {
+                    let mut client = self.client.clone();
+                    let mut request = self.request.clone();
+                    let control = self.control.clone();
+
+                    let fut = async move {
+                        request.controls.push(control.try_into()?);
+                        client.search(request).await
+                    };
+                    self.state = State::Searching(Box::pin(fut));
+                }
+                State::Searching(ref mut fut) => match Pin::new(fut).poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(err)) => {
+                        self.state = State::Finished;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    Poll::Ready(Ok(entries)) => self.state = State::Streaming(entries),
+                },
+                State::Streaming(ref mut entries) => match Pin::new(&mut *entries).poll_next(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(entry))) => return Poll::Ready(Some(Ok(entry))),
+                    Poll::Ready(Some(Err(err))) => {
+                        self.state = State::Finished;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    Poll::Ready(None) => {
+                        let State::Streaming(entries) = std::mem::replace(&mut self.state, State::Finished) else {
+                            unreachable!()
+                        };
+                        if let Err(err) = self.round_done(&entries) {
+                            return Poll::Ready(Some(Err(err)));
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::TryStreamExt;
+    use parking_lot::Mutex;
+    use rasn_ldap::{Control, LdapMessage, ProtocolOp, ResultCode, SearchResultDone, SearchResultEntry};
+
+    use super::*;
+    use crate::mock::{MockServer, ldap_result};
+
+    // Server which returns one entry per round and reports more data until the second round.
+    // The cookies received in the requests are recorded
+    async fn start_server(cookies: Arc<Mutex<Vec<Vec<u8>>>>) -> MockServer {
+        MockServer::start(move |_, msg| {
+            let ProtocolOp::SearchRequest(_) = msg.protocol_op else {
+                return Vec::new();
+            };
+            let request = msg
+                .controls
+                .and_then(|c| DirSyncResponseControl::from_controls(&c))
+                .unwrap()
+                .unwrap();
+            cookies.lock().push(request.cookie.clone());
+
+            let (dn, more, cookie) = match request.cookie.as_slice() {
+                b"" => ("cn=first", 1, "c1"),
+                _ => ("cn=second", 0, "c2"),
+            };
+            // the response value has the same layout as the request one
+            let control: Control = DirSyncControl::new().flags(more).cookie(cookie).try_into().unwrap();
+            let entry = SearchResultEntry::new(dn.into(), Vec::new());
+            let mut done = LdapMessage::new(
+                msg.message_id,
+                ProtocolOp::SearchResDone(SearchResultDone(ldap_result(ResultCode::Success))),
+            );
+            done.controls = Some(vec![control]);
+            vec![
+                LdapMessage::new(msg.message_id, ProtocolOp::SearchResEntry(entry)),
+                done,
+            ]
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_dirsync_rounds() {
+        let cookies = Arc::new(Mutex::new(Vec::new()));
+        let server = start_server(cookies.clone()).await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let mut entries = client.search_dirsync(SearchRequest::root_dse(), DirSyncControl::new());
+        let dns = (&mut entries).map_ok(|e| e.dn).try_collect::<Vec<_>>().await.unwrap();
+
+        assert_eq!(dns, ["cn=first", "cn=second"]);
+        assert_eq!(*cookies.lock(), [b"".to_vec(), b"c1".to_vec()]);
+        assert_eq!(entries.cookie(), b"c2");
+    }
+}
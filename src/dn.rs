@@ -0,0 +1,598 @@
+//! Distinguished names (RFC4514)
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::error::Error;
+
+const SPECIAL_CHARS: &[u8] = b"\"+,;<>\\=#";
+
+/// Escape an attribute value for use in a DN string
+pub fn escape_dn_value<S: AsRef<str>>(value: S) -> String {
+    let value = value.as_ref();
+    let mut result = String::with_capacity(value.len());
+    let last = value.len().saturating_sub(1);
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' | '+' | ',' | ';' | '<' | '>' | '\\' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '#' if i == 0 => result.push_str("\\#"),
+            ' ' if i == 0 || i == last => result.push_str("\\ "),
+            '\0' => result.push_str("\\00"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn hex_value(hex: &[u8]) -> Option<u8> {
+    u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+fn hex_string(value: &[u8]) -> String {
+    value.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// Collapse insignificant whitespace and fold the case
+fn normalize_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Attribute value of the RDN component
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AttributeValueKind {
+    /// String value
+    Text(String),
+    /// BER-encoded value, represented in the hex form `#0403616263`
+    Ber(Vec<u8>),
+}
+
+impl fmt::Display for AttributeValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeValueKind::Text(value) => f.write_str(&escape_dn_value(value)),
+            AttributeValueKind::Ber(value) => write!(f, "#{}", hex_string(value)),
+        }
+    }
+}
+
+/// A single attribute type and value pair of the RDN
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AttributeTypeAndValue {
+    /// Attribute type, a short name or a numeric OID
+    pub attr: String,
+    /// Attribute value
+    pub value: AttributeValueKind,
+}
+
+impl AttributeTypeAndValue {
+    /// Create a new pair with the string value
+    pub fn new<A, V>(attr: A, value: V) -> Self
+    where
+        A: AsRef<str>,
+        V: AsRef<str>,
+    {
+        Self {
+            attr: attr.as_ref().to_owned(),
+            value: AttributeValueKind::Text(value.as_ref().to_owned()),
+        }
+    }
+
+    fn normalized(&self) -> String {
+        let value = match self.value {
+            AttributeValueKind::Text(ref value) => escape_dn_value(normalize_value(value)),
+            AttributeValueKind::Ber(ref value) => format!("#{}", hex_string(value)),
+        };
+        format!("{}={}", self.attr.to_ascii_lowercase(), value)
+    }
+}
+
+impl fmt::Display for AttributeTypeAndValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.attr, self.value)
+    }
+}
+
+/// Relative distinguished name, possibly multi-valued
+#[derive(Clone, Debug)]
+pub struct Rdn {
+    values: Vec<AttributeTypeAndValue>,
+    repr: String,
+}
+
+impl Rdn {
+    /// Create a single-valued RDN
+    pub fn new<A, V>(attr: A, value: V) -> Self
+    where
+        A: AsRef<str>,
+        V: AsRef<str>,
+    {
+        Self::from_values(vec![AttributeTypeAndValue::new(attr, value)])
+    }
+
+    /// Create an RDN from the list of attribute type and value pairs
+    pub fn from_values(values: Vec<AttributeTypeAndValue>) -> Self {
+        let repr = values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("+");
+        Self { values, repr }
+    }
+
+    /// Add another attribute type and value pair, making a multi-valued RDN
+    pub fn and<A, V>(mut self, attr: A, value: V) -> Self
+    where
+        A: AsRef<str>,
+        V: AsRef<str>,
+    {
+        self.values.push(AttributeTypeAndValue::new(attr, value));
+        Self::from_values(self.values)
+    }
+
+    /// Return attribute type and value pairs
+    pub fn values(&self) -> &[AttributeTypeAndValue] {
+        &self.values
+    }
+
+    /// Return true if the RDN has more than one attribute type and value pair
+    pub fn is_multi_valued(&self) -> bool {
+        self.values.len() > 1
+    }
+
+    /// Return the normalized string representation used for comparison
+    pub fn normalized(&self) -> String {
+        let mut values = self.values.iter().map(|v| v.normalized()).collect::<Vec<_>>();
+        values.sort();
+        values.join("+")
+    }
+}
+
+impl PartialEq for Rdn {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized() == other.normalized()
+    }
+}
+
+impl Eq for Rdn {}
+
+impl Hash for Rdn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state)
+    }
+}
+
+impl AsRef<str> for Rdn {
+    fn as_ref(&self) -> &str {
+        &self.repr
+    }
+}
+
+impl fmt::Display for Rdn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.repr)
+    }
+}
+
+impl FromStr for Rdn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dn = Dn::from_str(s)?;
+        match <[Rdn; 1]>::try_from(dn.rdns) {
+            Ok([rdn]) => Ok(rdn),
+            Err(_) => Err(Error::InvalidDn(s.to_owned())),
+        }
+    }
+}
+
+/// Distinguished name. The first RDN is the most specific one.
+/// Comparison is case-insensitive and ignores insignificant whitespace.
+#[derive(Clone, Debug, Default)]
+pub struct Dn {
+    rdns: Vec<Rdn>,
+    repr: String,
+}
+
+impl Dn {
+    /// Create an empty DN which represents the root DSE
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Create a DN from the list of RDNs, the most specific one first
+    pub fn from_rdns<I: IntoIterator<Item = Rdn>>(rdns: I) -> Self {
+        let rdns = rdns.into_iter().collect::<Vec<_>>();
+        let repr = rdns.iter().map(|rdn| rdn.as_ref()).collect::<Vec<_>>().join(",");
+        Self { rdns, repr }
+    }
+
+    /// Parse a DN string
+    pub fn parse<S: AsRef<str>>(dn: S) -> Result<Self, Error> {
+        dn.as_ref().parse()
+    }
+
+    /// Return the list of RDNs, the most specific one first
+    pub fn rdns(&self) -> &[Rdn] {
+        &self.rdns
+    }
+
+    /// Return the most specific RDN
+    pub fn rdn(&self) -> Option<&Rdn> {
+        self.rdns.first()
+    }
+
+    /// Return true if this is an empty DN
+    pub fn is_root(&self) -> bool {
+        self.rdns.is_empty()
+    }
+
+    /// Return the number of RDNs
+    pub fn len(&self) -> usize {
+        self.rdns.len()
+    }
+
+    /// Return true if the DN has no RDNs
+    pub fn is_empty(&self) -> bool {
+        self.rdns.is_empty()
+    }
+
+    /// Return the parent DN or `None` for the root DN
+    pub fn parent(&self) -> Option<Dn> {
+        if self.rdns.is_empty() {
+            None
+        } else {
+            Some(Self::from_rdns(self.rdns[1..].iter().cloned()))
+        }
+    }
+
+    /// Create a child DN by prepending the RDN
+    pub fn child(&self, rdn: Rdn) -> Dn {
+        Self::from_rdns(std::iter::once(rdn).chain(self.rdns.iter().cloned()))
+    }
+
+    /// Return an iterator over the ancestors of this DN, starting from the parent
+    pub fn ancestors(&self) -> impl Iterator<Item = Dn> + '_ {
+        (1..=self.rdns.len()).map(|i| Self::from_rdns(self.rdns[i..].iter().cloned()))
+    }
+
+    /// Return true if this DN is a direct parent of the other one
+    pub fn is_parent_of(&self, other: &Dn) -> bool {
+        other.len() == self.len() + 1 && self.is_ancestor_of(other)
+    }
+
+    /// Return true if this DN is an ancestor of the other one
+    pub fn is_ancestor_of(&self, other: &Dn) -> bool {
+        other.len() > self.len() && other.rdns[other.len() - self.len()..] == self.rdns[..]
+    }
+
+    /// Return true if this DN is a descendant of the other one
+    pub fn is_descendant_of(&self, other: &Dn) -> bool {
+        other.is_ancestor_of(self)
+    }
+
+    /// Return the normalized string representation used for comparison
+    pub fn normalized(&self) -> String {
+        self.rdns
+            .iter()
+            .map(|rdn| rdn.normalized())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl PartialEq for Dn {
+    fn eq(&self, other: &Self) -> bool {
+        self.rdns == other.rdns
+    }
+}
+
+impl Eq for Dn {}
+
+impl Hash for Dn {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rdns.hash(state)
+    }
+}
+
+impl AsRef<str> for Dn {
+    fn as_ref(&self) -> &str {
+        &self.repr
+    }
+}
+
+impl fmt::Display for Dn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.repr)
+    }
+}
+
+impl From<Dn> for String {
+    fn from(dn: Dn) -> Self {
+        dn.repr
+    }
+}
+
+impl FromStr for Dn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DnParser::new(s.as_bytes())
+            .parse()
+            .map(Self::from_rdns)
+            .ok_or_else(|| Error::InvalidDn(s.to_owned()))
+    }
+}
+
+impl TryFrom<&str> for Dn {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+struct DnParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DnParser<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn parse(mut self) -> Option<Vec<Rdn>> {
+        let mut rdns = Vec::new();
+        self.skip_spaces();
+        if self.peek().is_none() {
+            return Some(rdns);
+        }
+        loop {
+            let mut values = vec![self.parse_pair()?];
+            loop {
+                match self.peek() {
+                    Some(b'+') => {
+                        self.pos += 1;
+                        values.push(self.parse_pair()?);
+                    }
+                    Some(b',') | Some(b';') => {
+                        self.pos += 1;
+                        rdns.push(Rdn::from_values(values));
+                        break;
+                    }
+                    None => {
+                        rdns.push(Rdn::from_values(values));
+                        return Some(rdns);
+                    }
+                    _ => return None,
+                }
+            }
+        }
+    }
+
+    fn parse_pair(&mut self) -> Option<AttributeTypeAndValue> {
+        self.skip_spaces();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == b'-' || c == b'.' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let attr = std::str::from_utf8(&self.input[start..self.pos]).ok()?;
+        if attr.is_empty() {
+            return None;
+        }
+        self.skip_spaces();
+        if self.peek() != Some(b'=') {
+            return None;
+        }
+        self.pos += 1;
+        self.skip_spaces();
+
+        let value = match self.peek() {
+            Some(b'#') => self.parse_hex_value()?,
+            Some(b'"') => self.parse_quoted_value()?,
+            _ => self.parse_string_value()?,
+        };
+        self.skip_spaces();
+
+        Some(AttributeTypeAndValue {
+            attr: attr.to_owned(),
+            value,
+        })
+    }
+
+    fn parse_hex_value(&mut self) -> Option<AttributeValueKind> {
+        self.pos += 1;
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let hex = &self.input[start..self.pos];
+        if hex.is_empty() || !hex.len().is_multiple_of(2) {
+            return None;
+        }
+        let value = hex.chunks(2).map(hex_value).collect::<Option<Vec<_>>>()?;
+        Some(AttributeValueKind::Ber(value))
+    }
+
+    fn parse_quoted_value(&mut self) -> Option<AttributeValueKind> {
+        self.pos += 1;
+        let mut value = Vec::new();
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.pos += 1;
+                    break;
+                }
+                b'\\' => value.push(self.parse_escape()?),
+                c => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(AttributeValueKind::Text(String::from_utf8(value).ok()?))
+    }
+
+    fn parse_escape(&mut self) -> Option<u8> {
+        self.pos += 1;
+        let c = self.peek()?;
+        if c == b' ' || SPECIAL_CHARS.contains(&c) {
+            self.pos += 1;
+            Some(c)
+        } else {
+            let value = hex_value(self.input.get(self.pos..self.pos + 2)?)?;
+            self.pos += 2;
+            Some(value)
+        }
+    }
+
+    fn parse_string_value(&mut self) -> Option<AttributeValueKind> {
+        let mut value = Vec::new();
+        // length of the value without the unescaped trailing spaces
+        let mut significant = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                b',' | b';' | b'+' => break,
+                b'\\' => {
+                    value.push(self.parse_escape()?);
+                    significant = value.len();
+                }
+                b'"' | b'<' | b'>' | b'\0' => return None,
+                _ => {
+                    value.push(c);
+                    self.pos += 1;
+                    if c != b' ' {
+                        significant = value.len();
+                    }
+                }
+            }
+        }
+        value.truncate(significant);
+        Some(AttributeValueKind::Text(String::from_utf8(value).ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dn() {
+        let dn = Dn::parse("CN=Steve Kille , O=Isode Limited;C=GB").unwrap();
+        assert_eq!(dn.len(), 3);
+        assert_eq!(dn.to_string(), "CN=Steve Kille,O=Isode Limited,C=GB");
+
+        let dn = Dn::parse("OU=Sales+CN=J. Smith,DC=example,DC=net").unwrap();
+        assert!(dn.rdn().unwrap().is_multi_valued());
+        assert_eq!(
+            dn.rdn().unwrap().values(),
+            &[
+                AttributeTypeAndValue::new("OU", "Sales"),
+                AttributeTypeAndValue::new("CN", "J. Smith")
+            ]
+        );
+
+        let dn = Dn::parse(r#"CN=James \"Jim\" Smith\, III,DC=example,DC=net"#).unwrap();
+        assert_eq!(
+            dn.rdns()[0].values()[0].value,
+            AttributeValueKind::Text(r#"James "Jim" Smith, III"#.to_owned())
+        );
+        assert_eq!(dn.to_string(), r#"CN=James \"Jim\" Smith\, III,DC=example,DC=net"#);
+
+        let dn = Dn::parse(r"CN=Before\0dAfter,DC=example,DC=net").unwrap();
+        assert_eq!(
+            dn.rdns()[0].values()[0].value,
+            AttributeValueKind::Text("Before\rAfter".to_owned())
+        );
+
+        let dn = Dn::parse("1.3.6.1.4.1.1466.0=#04024869").unwrap();
+        assert_eq!(
+            dn.rdns()[0].values()[0].value,
+            AttributeValueKind::Ber(vec![4, 2, 0x48, 0x69])
+        );
+        assert_eq!(dn.to_string(), "1.3.6.1.4.1.1466.0=#04024869");
+
+        let dn = Dn::parse(r"CN=Lu\C4\8Di\C4\87").unwrap();
+        assert_eq!(
+            dn.rdns()[0].values()[0].value,
+            AttributeValueKind::Text("Lučić".to_owned())
+        );
+
+        let dn = Dn::parse(r"cn=\ leading and trailing\ ").unwrap();
+        assert_eq!(
+            dn.rdns()[0].values()[0].value,
+            AttributeValueKind::Text(" leading and trailing ".to_owned())
+        );
+
+        assert!(Dn::parse("").unwrap().is_root());
+        assert!(Dn::parse("cn").is_err());
+        assert!(Dn::parse("cn=a,").is_err());
+        assert!(Dn::parse("=a").is_err());
+        assert!(Dn::parse(r"cn=a\zz").is_err());
+        assert!(Dn::parse("cn=#abc").is_err());
+    }
+
+    #[test]
+    fn test_escape_dn_value() {
+        assert_eq!(escape_dn_value("Smith, John"), r"Smith\, John");
+        assert_eq!(escape_dn_value("#1 <a+b>; \\ "), r"\#1 \<a\+b\>\; \\\ ");
+        assert_eq!(escape_dn_value(" x"), r"\ x");
+
+        let rdn = Rdn::new("cn", "Smith, John");
+        let dn = Dn::parse("ou=People,dc=example,dc=com").unwrap().child(rdn.clone());
+        assert_eq!(dn.to_string(), r"cn=Smith\, John,ou=People,dc=example,dc=com");
+        assert_eq!(Dn::parse(&dn).unwrap().rdn(), Some(&rdn));
+    }
+
+    #[test]
+    fn test_dn_navigation() {
+        let dn = Dn::parse("cn=John,ou=People,dc=example,dc=com").unwrap();
+        let parent = dn.parent().unwrap();
+        assert_eq!(parent.to_string(), "ou=People,dc=example,dc=com");
+        assert!(parent.is_parent_of(&dn));
+        assert!(dn.is_descendant_of(&parent));
+
+        let base = Dn::parse("DC=Example, DC=COM").unwrap();
+        assert!(base.is_ancestor_of(&dn));
+        assert!(!base.is_parent_of(&dn));
+        assert!(!dn.is_ancestor_of(&dn));
+        assert!(Dn::root().is_ancestor_of(&dn));
+
+        let ancestors = dn.ancestors().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            ancestors,
+            vec!["ou=People,dc=example,dc=com", "dc=example,dc=com", "dc=com", ""]
+        );
+        assert_eq!(Dn::root().parent(), None);
+    }
+
+    #[test]
+    fn test_dn_equality() {
+        let a = Dn::parse("CN=John  Smith,OU=People,DC=Example,DC=com").unwrap();
+        let b = Dn::parse("cn=john smith, ou=people, dc=example, dc=COM").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.normalized(), "cn=john smith,ou=people,dc=example,dc=com");
+
+        let a = Dn::parse("ou=Sales+cn=J. Smith").unwrap();
+        let b = Dn::parse("CN=j. smith+OU=sales").unwrap();
+        assert_eq!(a, b);
+
+        assert_ne!(Dn::parse("cn=a,dc=com").unwrap(), Dn::parse("cn=b,dc=com").unwrap());
+    }
+}
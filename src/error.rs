@@ -4,9 +4,15 @@ use std::{error, fmt, io};
 
 use futures::channel::mpsc::SendError;
 use rasn::ber;
-use rasn_ldap::{BindResponse, LdapResult, ResultCode};
+use rasn_ldap::{BindResponse, Control, LdapResult, ResultCode};
 
-use crate::{channel::ChannelError, filter::Rule};
+use crate::{
+    OperationResult,
+    channel::ChannelError,
+    controls::{SortResultControl, VirtualListViewResponseControl},
+    extended::ExtendedResponse,
+    filter::FilterError,
+};
 
 /// LDAP operation error
 #[derive(Debug)]
@@ -17,6 +23,10 @@ pub struct OperationError {
     pub matched_dn: String,
     /// Diagnostic message
     pub diagnostic_message: String,
+    /// Referral URLs
+    pub referrals: Vec<String>,
+    /// Response controls, which may describe the failure
+    pub controls: Vec<Control>,
 }
 
 impl From<BindResponse> for OperationError {
@@ -25,6 +35,8 @@ impl From<BindResponse> for OperationError {
             result_code: r.result_code,
             matched_dn: r.matched_dn.0,
             diagnostic_message: r.diagnostic_message.0,
+            referrals: r.referral.unwrap_or_default().into_iter().map(|r| r.0).collect(),
+            controls: Vec::new(),
         }
     }
 }
@@ -35,6 +47,32 @@ impl From<LdapResult> for OperationError {
             result_code: r.result_code,
             matched_dn: r.matched_dn.0,
             diagnostic_message: r.diagnostic_message.0,
+            referrals: r.referral.unwrap_or_default().into_iter().map(|r| r.0).collect(),
+            controls: Vec::new(),
+        }
+    }
+}
+
+impl From<ExtendedResponse> for OperationError {
+    fn from(r: ExtendedResponse) -> Self {
+        OperationError {
+            result_code: r.result_code,
+            matched_dn: r.matched_dn,
+            diagnostic_message: r.diagnostic_message,
+            referrals: r.referrals,
+            controls: r.controls,
+        }
+    }
+}
+
+impl From<OperationResult> for OperationError {
+    fn from(r: OperationResult) -> Self {
+        OperationError {
+            result_code: r.result_code,
+            matched_dn: r.matched_dn,
+            diagnostic_message: r.diagnostic_message,
+            referrals: r.referrals,
+            controls: r.controls,
         }
     }
 }
@@ -50,11 +88,16 @@ pub enum Error {
     Send(SendError),
     InvalidMessageId,
     OperationFailed(OperationError),
-    InvalidFilter(pest::error::Error<Rule>),
+    InvalidFilter(FilterError),
     InvalidResponse,
     ConnectionClosed,
     GssApiError(String),
     NoSaslCredentials,
+    SortFailed(SortResultControl),
+    VirtualListViewFailed(VirtualListViewResponseControl),
+    InvalidUrl(String),
+    InvalidDn(String),
+    Timeout,
 }
 
 impl error::Error for Error {}
@@ -89,8 +132,8 @@ impl From<SendError> for Error {
     }
 }
 
-impl From<pest::error::Error<Rule>> for Error {
-    fn from(e: pest::error::Error<Rule>) -> Self {
+impl From<FilterError> for Error {
+    fn from(e: FilterError) -> Self {
         Error::InvalidFilter(e)
     }
 }
@@ -110,6 +153,11 @@ impl fmt::Display for Error {
             Error::ConnectionClosed => write!(f, "Connection closed"),
             Error::GssApiError(e) => write!(f, "{e}"),
             Error::NoSaslCredentials => write!(f, "No SASL credentials in response"),
+            Error::SortFailed(result) => write!(f, "Server-side sort failed: {result:?}"),
+            Error::VirtualListViewFailed(result) => write!(f, "Virtual list view failed: {result:?}"),
+            Error::InvalidUrl(url) => write!(f, "Invalid LDAP URL: {url}"),
+            Error::InvalidDn(dn) => write!(f, "Invalid distinguished name: {dn}"),
+            Error::Timeout => write!(f, "Operation timed out"),
         }
     }
 }
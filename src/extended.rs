@@ -0,0 +1,192 @@
+//! LDAP extended operations
+
+use bytes::Bytes;
+use rasn::{Decode, Decoder, Encode, ber, types::*};
+use rasn_ldap::{Control, LdapMessage, ProtocolOp, ResultCode};
+
+use crate::{error::Error, oid};
+
+/// Extended operation response
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExtendedResponse {
+    /// Result code
+    pub result_code: ResultCode,
+    /// Matched DN
+    pub matched_dn: String,
+    /// Diagnostic message
+    pub diagnostic_message: String,
+    /// Referral URLs
+    pub referrals: Vec<String>,
+    /// Response name (OID)
+    pub name: Option<Bytes>,
+    /// Response value
+    pub value: Option<Bytes>,
+    /// Response controls
+    pub controls: Vec<Control>,
+}
+
+impl TryFrom<LdapMessage> for ExtendedResponse {
+    type Error = Error;
+
+    fn try_from(msg: LdapMessage) -> Result<Self, Self::Error> {
+        match msg.protocol_op {
+            ProtocolOp::ExtendedResp(resp) => Ok(ExtendedResponse {
+                result_code: resp.result_code,
+                matched_dn: resp.matched_dn.0,
+                diagnostic_message: resp.diagnostic_message.0,
+                referrals: resp.referral.unwrap_or_default().into_iter().map(|r| r.0).collect(),
+                name: resp.response_name.map(|v| Bytes::copy_from_slice(&v)),
+                value: resp.response_value.map(|v| Bytes::copy_from_slice(&v)),
+                controls: msg.controls.unwrap_or_default(),
+            }),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+}
+
+/// Typed extended operation.
+/// Implement this trait to define custom extended requests and responses.
+pub trait ExtendedOperation {
+    /// Response type returned by the operation
+    type Response;
+
+    /// Request OID
+    const OID: &'static [u8];
+
+    /// Return the BER-encoded request value
+    fn value(&self) -> Result<Option<Bytes>, Error>;
+
+    /// Convert a successful extended response into a typed response
+    fn parse_response(response: ExtendedResponse) -> Result<Self::Response, Error>;
+}
+
+/// 'Who am I' extended operation (RFC4532)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WhoAmI;
+
+impl ExtendedOperation for WhoAmI {
+    type Response = Option<String>;
+
+    const OID: &'static [u8] = oid::WHOAMI_OID;
+
+    fn value(&self) -> Result<Option<Bytes>, Error> {
+        Ok(None)
+    }
+
+    fn parse_response(response: ExtendedResponse) -> Result<Self::Response, Error> {
+        Ok(response.value.map(|v| String::from_utf8_lossy(&v).into_owned()))
+    }
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, Default, PartialEq, Eq)]
+struct PasswdModifyRequestValue {
+    #[rasn(tag(0))]
+    user_identity: Option<OctetString>,
+    #[rasn(tag(1))]
+    old_passwd: Option<OctetString>,
+    #[rasn(tag(2))]
+    new_passwd: Option<OctetString>,
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, Default, PartialEq, Eq)]
+struct PasswdModifyResponseValue {
+    #[rasn(tag(0))]
+    gen_passwd: Option<OctetString>,
+}
+
+/// Password modify extended operation (RFC3062).
+/// If the new password is omitted the server generates one and returns it in the response.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PasswordModify {
+    pub(crate) user_identity: Option<String>,
+    pub(crate) old_password: Option<Vec<u8>>,
+    pub(crate) new_password: Option<Vec<u8>>,
+}
+
+impl PasswordModify {
+    /// Create a password modify request for the currently bound user with a server-generated password
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the user identity, typically a DN. The default is the currently bound user
+    pub fn user_identity<S: AsRef<str>>(mut self, user_identity: S) -> Self {
+        self.user_identity = Some(user_identity.as_ref().to_owned());
+        self
+    }
+
+    /// Set the old password
+    pub fn old_password<P: AsRef<[u8]>>(mut self, old_password: P) -> Self {
+        self.old_password = Some(old_password.as_ref().to_vec());
+        self
+    }
+
+    /// Set the new password
+    pub fn new_password<P: AsRef<[u8]>>(mut self, new_password: P) -> Self {
+        self.new_password = Some(new_password.as_ref().to_vec());
+        self
+    }
+}
+
+impl ExtendedOperation for PasswordModify {
+    type Response = Option<Vec<u8>>;
+
+    const OID: &'static [u8] = oid::PASSWORD_MODIFY_OID;
+
+    fn value(&self) -> Result<Option<Bytes>, Error> {
+        let value = PasswdModifyRequestValue {
+            user_identity: self.user_identity.as_ref().map(|s| s.as_bytes().to_vec().into()),
+            old_passwd: self.old_password.clone().map(Into::into),
+            new_passwd: self.new_password.clone().map(Into::into),
+        };
+        Ok(Some(ber::encode(&value)?.into()))
+    }
+
+    fn parse_response(response: ExtendedResponse) -> Result<Self::Response, Error> {
+        match response.value {
+            Some(value) => {
+                let value = ber::decode::<PasswdModifyResponseValue>(&value)?;
+                Ok(value.gen_passwd.map(|p| p.to_vec()))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(value: Option<&[u8]>) -> ExtendedResponse {
+        ExtendedResponse {
+            result_code: ResultCode::Success,
+            matched_dn: String::new(),
+            diagnostic_message: String::new(),
+            referrals: Vec::new(),
+            name: None,
+            value: value.map(Bytes::copy_from_slice),
+            controls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_password_modify_request() {
+        let op = PasswordModify::new().user_identity("u").new_password("p");
+        assert_eq!(op.value().unwrap().unwrap().as_ref(), b"\x30\x06\x80\x01u\x82\x01p");
+
+        let op = PasswordModify::new();
+        assert_eq!(op.value().unwrap().unwrap().as_ref(), b"\x30\x00");
+    }
+
+    #[test]
+    fn test_password_modify_response() {
+        let gen_passwd = PasswordModify::parse_response(response(Some(b"\x30\x05\x80\x03abc"))).unwrap();
+        assert_eq!(gen_passwd.as_deref(), Some(b"abc".as_slice()));
+
+        assert_eq!(
+            PasswordModify::parse_response(response(Some(b"\x30\x00"))).unwrap(),
+            None
+        );
+        assert_eq!(PasswordModify::parse_response(response(None)).unwrap(), None);
+    }
+}
@@ -1,11 +1,13 @@
-use std::{borrow::Cow, sync::LazyLock};
+//! LDAP search filters (RFC4515)
+
+use std::{borrow::Cow, fmt, sync::LazyLock};
 
 use bytes::Bytes;
 use pest::{
     Parser,
+    error::{ErrorVariant, InputLocation},
     iterators::{Pair, Pairs},
 };
-use pest_derive::Parser;
 use rasn::prelude::*;
 use rasn_ldap::{
     AssertionValue, AttributeValueAssertion, Filter, LdapString, MatchingRuleAssertion, SubstringChoice,
@@ -15,6 +17,23 @@ use regex::bytes::{Captures, Regex};
 
 use crate::error::Error;
 
+use parser::{FilterParser, Rule};
+
+pub use eval::*;
+pub use normalize::*;
+
+mod eval;
+mod normalize;
+
+// The generated grammar rules are kept private, they are not a part of the public API
+mod parser {
+    use pest_derive::Parser;
+
+    #[derive(Parser)]
+    #[grammar = "filter.pest"]
+    pub(crate) struct FilterParser;
+}
+
 type RulePair<'a> = Pair<'a, Rule>;
 type RulePairs<'a> = Pairs<'a, Rule>;
 
@@ -39,12 +58,68 @@ fn unescape(s: &[u8]) -> Cow<'_, [u8]> {
     HEX_RE.replace_all(s, |caps: &Captures| [hex2b(&caps[1])])
 }
 
-#[derive(Parser)]
-#[grammar = "filter.pest"]
-pub struct FilterParser;
+/// Filter parse error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilterError {
+    /// Character offset of the offending input, 0-based
+    pub position: usize,
+    /// Error description
+    pub message: String,
+}
+
+impl FilterError {
+    fn new(filter: &str, e: pest::error::Error<Rule>) -> Self {
+        let offset = match e.location {
+            InputLocation::Pos(pos) => pos,
+            InputLocation::Span((start, _)) => start,
+        };
+        let found = match filter[offset..].chars().next() {
+            Some(c) => format!("unexpected '{c}'"),
+            None => "unexpected end of filter".to_owned(),
+        };
+        let message = match e.variant {
+            ErrorVariant::ParsingError { positives, .. } if !positives.is_empty() => {
+                let mut expected = positives.iter().map(rule_name).collect::<Vec<_>>();
+                expected.dedup();
+                format!("{found}, expected {}", expected.join(" or "))
+            }
+            ErrorVariant::CustomError { message } => message,
+            _ => found,
+        };
+        Self {
+            position: filter[..offset].chars().count(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid filter at position {}: {}", self.position, self.message)
+    }
+}
+
+fn rule_name(rule: &Rule) -> &'static str {
+    match rule {
+        Rule::ident => "attribute description",
+        Rule::ruleid => "matching rule",
+        Rule::string | Rule::initial | Rule::any | Rule::final_ => "assertion value",
+        Rule::equal | Rule::approx | Rule::greater | Rule::less => "filter type",
+        Rule::dnattr => "':dn'",
+        Rule::close => "')'",
+        Rule::and | Rule::or | Rule::not | Rule::simple | Rule::present | Rule::substring | Rule::extensible => {
+            "filter"
+        }
+        Rule::EOI => "end of filter",
+        _ => "filter component",
+    }
+}
 
+/// Parse a filter string into the filter structure
 pub fn parse_filter<S: AsRef<str>>(filter: S) -> Result<Filter, Error> {
-    let mut parsed = FilterParser::parse(Rule::rfc2254, filter.as_ref())?;
+    let filter = filter.as_ref();
+    let mut parsed =
+        FilterParser::parse(Rule::rfc2254, filter).map_err(|e| Error::InvalidFilter(FilterError::new(filter, e)))?;
     Ok(parse_rule(parsed.next().expect("No top level rule")))
 }
 
@@ -115,16 +190,211 @@ fn parse_simple(pairs: RulePairs) -> Filter {
 
 #[allow(clippy::mutable_key_type)]
 fn parse_set(pairs: RulePairs) -> SetOf<Filter> {
-    pairs.map(parse_rule).collect::<Vec<_>>().into()
+    pairs
+        .filter(|pair| pair.as_rule() != Rule::close)
+        .map(parse_rule)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+// Returns None for the filter choices which are unknown to this library
+fn write_filter(filter: &Filter, out: &mut String) -> Option<()> {
+    out.push('(');
+    match filter {
+        Filter::And(filters) => {
+            out.push('&');
+            for f in filters.to_vec() {
+                write_filter(f, out)?;
+            }
+        }
+        Filter::Or(filters) => {
+            out.push('|');
+            for f in filters.to_vec() {
+                write_filter(f, out)?;
+            }
+        }
+        Filter::Not(filter) => {
+            out.push('!');
+            write_filter(filter, out)?;
+        }
+        Filter::EqualityMatch(ava) => write_assertion(ava, "=", out),
+        Filter::GreaterOrEqual(ava) => write_assertion(ava, ">=", out),
+        Filter::LessOrEqual(ava) => write_assertion(ava, "<=", out),
+        Filter::ApproxMatch(ava) => write_assertion(ava, "~=", out),
+        Filter::Present(attr) => {
+            out.push_str(&attr.0);
+            out.push_str("=*");
+        }
+        Filter::Substrings(substrings) => {
+            out.push_str(&substrings.r#type.0);
+            out.push('=');
+            let mut choices = substrings.substrings.iter().peekable();
+            if let Some(SubstringChoice::Initial(v)) = choices.peek() {
+                out.push_str(&escape_filter_value(v));
+                choices.next();
+            }
+            out.push('*');
+            for choice in choices {
+                match choice {
+                    SubstringChoice::Initial(v) | SubstringChoice::Any(v) => {
+                        out.push_str(&escape_filter_value(v));
+                        out.push('*');
+                    }
+                    SubstringChoice::Final(v) => out.push_str(&escape_filter_value(v)),
+                    _ => return None,
+                }
+            }
+        }
+        Filter::ExtensibleMatch(mra) => {
+            if let Some(ref attr) = mra.r#type {
+                out.push_str(&attr.0);
+            }
+            if mra.dn_attributes {
+                out.push_str(":dn");
+            }
+            if let Some(ref rule) = mra.matching_rule {
+                out.push(':');
+                out.push_str(&rule.0);
+            }
+            out.push_str(":=");
+            out.push_str(&escape_filter_value(&mra.match_value));
+        }
+        _ => return None,
+    }
+    out.push(')');
+    Some(())
+}
+
+fn write_assertion(ava: &AttributeValueAssertion, op: &str, out: &mut String) {
+    out.push_str(&ava.attribute_desc.0);
+    out.push_str(op);
+    out.push_str(&escape_filter_value(&ava.assertion_value));
+}
+
+/// Render a filter into its RFC4515 string representation.
+/// Returns None if the filter contains a choice which has no string representation
+pub fn render_filter(filter: &Filter) -> Option<String> {
+    let mut out = String::new();
+    write_filter(filter, &mut out)?;
+    Some(out)
+}
+
+/// Escape an assertion value for use in a filter string.
+/// Special characters, control characters and non-UTF8 bytes are hex-escaped.
+pub fn escape_filter_value<V: AsRef<[u8]>>(value: V) -> String {
+    let value = value.as_ref();
+    let mut result = String::with_capacity(value.len());
+    match std::str::from_utf8(value) {
+        Ok(s) => {
+            for c in s.chars() {
+                match c {
+                    '*' | '(' | ')' | '\\' => result.push_str(&format!("\\{:02x}", c as u8)),
+                    c if c.is_control() => {
+                        let mut buf = [0; 4];
+                        for b in c.encode_utf8(&mut buf).bytes() {
+                            result.push_str(&format!("\\{b:02x}"));
+                        }
+                    }
+                    c => result.push(c),
+                }
+            }
+        }
+        Err(_) => {
+            for &b in value {
+                if (b.is_ascii_graphic() && !b"*()\\".contains(&b)) || b == b' ' {
+                    result.push(b as char);
+                } else {
+                    result.push_str(&format!("\\{b:02x}"));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn assertion<A: AsRef<str>, V: AsRef<[u8]>>(attr: A, value: V) -> AttributeValueAssertion {
+    AttributeValueAssertion::new(attr.as_ref().into(), value.as_ref().into())
+}
+
+/// Create a filter which matches if all of the given filters match: `(&(f1)(f2))`
+#[allow(clippy::mutable_key_type)]
+pub fn and<I: IntoIterator<Item = Filter>>(filters: I) -> Filter {
+    Filter::And(filters.into_iter().collect::<Vec<_>>().into())
+}
+
+/// Create a filter which matches if any of the given filters match: `(|(f1)(f2))`
+#[allow(clippy::mutable_key_type)]
+pub fn or<I: IntoIterator<Item = Filter>>(filters: I) -> Filter {
+    Filter::Or(filters.into_iter().collect::<Vec<_>>().into())
+}
+
+/// Create a negation filter: `(!(f))`
+pub fn not(filter: Filter) -> Filter {
+    Filter::Not(Box::new(filter))
+}
+
+/// Create an equality filter: `(attr=value)`
+pub fn eq<A: AsRef<str>, V: AsRef<[u8]>>(attr: A, value: V) -> Filter {
+    Filter::EqualityMatch(assertion(attr, value))
+}
+
+/// Create a greater-or-equal filter: `(attr>=value)`
+pub fn ge<A: AsRef<str>, V: AsRef<[u8]>>(attr: A, value: V) -> Filter {
+    Filter::GreaterOrEqual(assertion(attr, value))
+}
+
+/// Create a less-or-equal filter: `(attr<=value)`
+pub fn le<A: AsRef<str>, V: AsRef<[u8]>>(attr: A, value: V) -> Filter {
+    Filter::LessOrEqual(assertion(attr, value))
+}
+
+/// Create an approximate match filter: `(attr~=value)`
+pub fn approx<A: AsRef<str>, V: AsRef<[u8]>>(attr: A, value: V) -> Filter {
+    Filter::ApproxMatch(assertion(attr, value))
+}
+
+/// Create a presence filter: `(attr=*)`
+pub fn present<A: AsRef<str>>(attr: A) -> Filter {
+    Filter::Present(attr.as_ref().into())
+}
+
+/// Create a substring filter: `(attr=initial*any1*any2*final)`
+pub fn substring<A, I, V>(attr: A, initial: Option<V>, any: I, final_: Option<V>) -> Filter
+where
+    A: AsRef<str>,
+    I: IntoIterator<Item = V>,
+    V: AsRef<[u8]>,
+{
+    let choices = initial
+        .map(|v| SubstringChoice::Initial(v.as_ref().into()))
+        .into_iter()
+        .chain(any.into_iter().map(|v| SubstringChoice::Any(v.as_ref().into())))
+        .chain(final_.map(|v| SubstringChoice::Final(v.as_ref().into())))
+        .collect();
+    Filter::Substrings(SubstringFilter::new(attr.as_ref().into(), choices))
+}
+
+/// Create an extensible match filter: `(attr:dn:rule:=value)`
+pub fn extensible<V: AsRef<[u8]>>(
+    matching_rule: Option<&str>,
+    attr: Option<&str>,
+    value: V,
+    dn_attributes: bool,
+) -> Filter {
+    Filter::ExtensibleMatch(MatchingRuleAssertion::new(
+        matching_rule.map(Into::into),
+        attr.map(Into::into),
+        value.as_ref().into(),
+        dn_attributes,
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parser() {
-        let test_filters = vec![
+    fn test_filters() -> Vec<(&'static str, Filter)> {
+        vec![
             (
                 r#"(cn=Babs Jensen\2a\30T\30\01)"#,
                 Filter::EqualityMatch(AttributeValueAssertion::new(
@@ -231,9 +501,12 @@ mod tests {
                     false,
                 )))),
             ),
-        ];
+        ]
+    }
 
-        for f in test_filters {
+    #[test]
+    fn test_parser() {
+        for f in test_filters() {
             assert_eq!(parse_filter(f.0).unwrap(), f.1);
         }
     }
@@ -275,4 +548,111 @@ mod tests {
         let decoded = unescape(hex);
         assert_eq!(decoded.as_ref(), b"hello\\\\gg");
     }
+
+    #[test]
+    fn test_builder() {
+        let filter = and([
+            eq("objectClass", "Person"),
+            or([eq("sn", "Jensen"), substring("cn", Some("Babs J"), [], None)]),
+        ]);
+        assert_eq!(
+            filter,
+            parse_filter("(&(objectClass=Person)(|(sn=Jensen)(cn=Babs J*)))").unwrap()
+        );
+
+        let filter = not(extensible(
+            Some("1.2.840.113556.1.4.803"),
+            Some("userAccountControl"),
+            "2",
+            false,
+        ));
+        assert_eq!(
+            filter,
+            parse_filter("(!(userAccountControl:1.2.840.113556.1.4.803:=2))").unwrap()
+        );
+
+        let filter = substring("o", Some("univ"), ["of", "mich"], Some("end"));
+        assert_eq!(filter, parse_filter("(o=univ*of*mich*end)").unwrap());
+
+        let user_input = "*)(uid=*";
+        assert_eq!(
+            eq("cn", user_input),
+            parse_filter(format!("(cn={})", escape_filter_value(user_input))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_escape_filter_value() {
+        assert_eq!(escape_filter_value("Babs (Jensen)*\\"), r"Babs \28Jensen\29\2a\5c");
+        assert_eq!(escape_filter_value("Lučić\0"), "Lučić\\00");
+        assert_eq!(escape_filter_value(b"\x01\x05a\xc9"), r"\01\05a\c9");
+    }
+
+    #[test]
+    fn test_render_filter() {
+        for f in test_filters() {
+            let rendered = render_filter(&f.1).unwrap();
+            assert_eq!(parse_filter(&rendered).unwrap(), f.1, "{rendered}");
+        }
+
+        let filters = [
+            "(&(objectClass=Person)(|(sn=Jensen)(cn=Babs J*)))",
+            "(o=univ*of*mich*end)",
+            "(cn=*mid*)",
+            "(cn=*end)",
+            "(sn:dn:2.4.6.8.10:=Barney Rubble)",
+            "(:dn:2.4.6.8.10:=Dino)",
+            "(!(userAccountControl:1.2.840.113556.1.4.803:=2))",
+            "(cn=Babs Jensen\\2a0T0\\01)",
+            "(objectSid=\\01\\05\\00\\00B\\b7\\a79)",
+            "(&(age>=18)(age<=65)(name~=john))",
+        ];
+        for filter in filters {
+            assert_eq!(render_filter(&parse_filter(filter).unwrap()).unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn test_attribute_descriptions() {
+        let filters = [
+            "(msDS-UserPasswordExpiryTimeComputed>=1)",
+            "(userCertificate;binary=\\82\\01)",
+            "(cn;lang-en=Babs*)",
+            "(2.5.4.3=foo)",
+            "(2.5.4.3;x-opt:caseExactMatch:=Foo)",
+            "(sn:dn:=Rubble)",
+            "(cn;lang-en=*)",
+        ];
+        for filter in filters {
+            assert_eq!(render_filter(&parse_filter(filter).unwrap()).unwrap(), filter);
+        }
+        assert_eq!(
+            parse_filter("(2.5.4.3=foo)").unwrap(),
+            Filter::EqualityMatch(AttributeValueAssertion::new("2.5.4.3".into(), b"foo".as_slice().into()))
+        );
+
+        assert!(parse_filter("(-cn=foo)").is_err());
+        assert!(parse_filter("(cn;=foo)").is_err());
+        assert!(parse_filter("(2.5.=foo)").is_err());
+    }
+
+    #[test]
+    fn test_filter_error_position() {
+        let Err(Error::InvalidFilter(e)) = parse_filter("(&(cn=foo)(sn=bar\\zz))") else {
+            panic!("Expected filter error");
+        };
+        assert_eq!(e.position, 17);
+
+        let Err(Error::InvalidFilter(e)) = parse_filter("(cn=foo") else {
+            panic!("Expected filter error");
+        };
+        assert_eq!(e.position, 7);
+        assert!(e.message.starts_with("unexpected end of filter"), "{e}");
+
+        let Err(Error::InvalidFilter(e)) = parse_filter("(&(c n=x))") else {
+            panic!("Expected filter error");
+        };
+        assert_eq!(e.position, 4);
+        assert!(e.message.starts_with("unexpected ' '"), "{e}");
+    }
 }
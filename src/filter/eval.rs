@@ -0,0 +1,463 @@
+//! In-memory filter evaluation
+
+use std::{cmp::Ordering, collections::HashMap, fmt, sync::Arc};
+
+use rasn_ldap::{AttributeValueAssertion, Filter, MatchingRuleAssertion, SubstringChoice, SubstringFilter};
+
+use crate::{dn::AttributeValueKind, error::Error, filter::parse_filter, model::SearchEntry};
+
+/// Active Directory bitwise AND matching rule
+pub const BIT_AND_RULE: &str = "1.2.840.113556.1.4.803";
+/// Active Directory bitwise OR matching rule
+pub const BIT_OR_RULE: &str = "1.2.840.113556.1.4.804";
+
+fn normalize(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn parse_int(value: &[u8]) -> Option<i64> {
+    std::str::from_utf8(value).ok()?.trim().parse().ok()
+}
+
+/// Matching rule used to compare attribute values with assertion values
+pub trait MatchingRule: Send + Sync {
+    /// Return true if the attribute value matches the assertion value
+    fn matches(&self, value: &[u8], assertion: &[u8]) -> bool;
+
+    /// Compare the attribute value with the assertion value, used by ordering filters
+    fn compare(&self, _value: &[u8], _assertion: &[u8]) -> Option<Ordering> {
+        None
+    }
+
+    /// Prepare a value for substring matching, `None` if the rule does not support substring filters
+    fn substring_value(&self, _value: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Case-insensitive string match ignoring insignificant whitespace, with integer-aware ordering
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaseIgnoreMatch;
+
+impl MatchingRule for CaseIgnoreMatch {
+    fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        normalize(value) == normalize(assertion)
+    }
+
+    fn compare(&self, value: &[u8], assertion: &[u8]) -> Option<Ordering> {
+        match (parse_int(value), parse_int(assertion)) {
+            (Some(value), Some(assertion)) => Some(value.cmp(&assertion)),
+            _ => Some(normalize(value).cmp(&normalize(assertion))),
+        }
+    }
+
+    fn substring_value(&self, value: &[u8]) -> Option<Vec<u8>> {
+        Some(normalize(value).into_bytes())
+    }
+}
+
+/// Exact octet match
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaseExactMatch;
+
+impl MatchingRule for CaseExactMatch {
+    fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        value == assertion
+    }
+
+    fn compare(&self, value: &[u8], assertion: &[u8]) -> Option<Ordering> {
+        Some(value.cmp(assertion))
+    }
+
+    fn substring_value(&self, value: &[u8]) -> Option<Vec<u8>> {
+        Some(value.to_vec())
+    }
+}
+
+/// Integer match
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IntegerMatch;
+
+impl MatchingRule for IntegerMatch {
+    fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        self.compare(value, assertion) == Some(Ordering::Equal)
+    }
+
+    fn compare(&self, value: &[u8], assertion: &[u8]) -> Option<Ordering> {
+        Some(parse_int(value)?.cmp(&parse_int(assertion)?))
+    }
+}
+
+/// Bitwise AND match: all bits of the assertion value are set in the attribute value
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BitAndMatch;
+
+impl MatchingRule for BitAndMatch {
+    fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        match (parse_int(value), parse_int(assertion)) {
+            (Some(value), Some(assertion)) => value & assertion == assertion,
+            _ => false,
+        }
+    }
+}
+
+/// Bitwise OR match: any bit of the assertion value is set in the attribute value
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BitOrMatch;
+
+impl MatchingRule for BitOrMatch {
+    fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+        match (parse_int(value), parse_int(assertion)) {
+            (Some(value), Some(assertion)) => value & assertion != 0,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SubstringKind {
+    Initial,
+    Any,
+    Final,
+}
+
+fn substrings_match(value: &[u8], choices: &[(SubstringKind, Vec<u8>)]) -> bool {
+    let mut rest = value;
+    for (kind, v) in choices {
+        match kind {
+            SubstringKind::Initial => match rest.strip_prefix(v.as_slice()) {
+                Some(r) => rest = r,
+                None => return false,
+            },
+            SubstringKind::Any if v.is_empty() => {}
+            SubstringKind::Any => match rest.windows(v.len()).position(|w| w == v.as_slice()) {
+                Some(pos) => rest = &rest[pos + v.len()..],
+                None => return false,
+            },
+            SubstringKind::Final => {
+                if !rest.ends_with(v) {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+// Attribute description matching: the base type is compared case-insensitively,
+// all options of the filter attribute must be present in the entry attribute
+fn attr_matches(filter_attr: &str, entry_attr: &str) -> bool {
+    let mut filter_parts = filter_attr.split(';');
+    let mut entry_parts = entry_attr.split(';');
+    if !filter_parts
+        .next()
+        .unwrap_or_default()
+        .eq_ignore_ascii_case(entry_parts.next().unwrap_or_default())
+    {
+        return false;
+    }
+    let entry_options = entry_parts.collect::<Vec<_>>();
+    filter_parts.all(|option| entry_options.iter().any(|o| o.eq_ignore_ascii_case(option)))
+}
+
+/// Evaluates search filters against entries locally, without a server.
+/// Attribute names are compared case-insensitively; the default matching rule is [CaseIgnoreMatch].
+/// Unknown extensible matching rules evaluate to undefined, which never matches.
+#[derive(Clone)]
+pub struct FilterEvaluator {
+    default_rule: Arc<dyn MatchingRule>,
+    attribute_rules: HashMap<String, Arc<dyn MatchingRule>>,
+    matching_rules: HashMap<String, Arc<dyn MatchingRule>>,
+}
+
+impl fmt::Debug for FilterEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilterEvaluator")
+            .field("attribute_rules", &self.attribute_rules.keys())
+            .field("matching_rules", &self.matching_rules.keys())
+            .finish()
+    }
+}
+
+impl Default for FilterEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FilterEvaluator {
+    /// Create an evaluator with the built-in matching rules
+    pub fn new() -> Self {
+        let evaluator = Self {
+            default_rule: Arc::new(CaseIgnoreMatch),
+            attribute_rules: HashMap::new(),
+            matching_rules: HashMap::new(),
+        };
+        evaluator
+            .matching_rule(BIT_AND_RULE, BitAndMatch)
+            .matching_rule(BIT_OR_RULE, BitOrMatch)
+            .matching_rule("2.5.13.2", CaseIgnoreMatch)
+            .matching_rule("caseIgnoreMatch", CaseIgnoreMatch)
+            .matching_rule("2.5.13.5", CaseExactMatch)
+            .matching_rule("caseExactMatch", CaseExactMatch)
+            .matching_rule("2.5.13.14", IntegerMatch)
+            .matching_rule("integerMatch", IntegerMatch)
+    }
+
+    /// Register a matching rule for extensible match filters, by OID or name
+    pub fn matching_rule<S: AsRef<str>, R: MatchingRule + 'static>(mut self, rule_id: S, rule: R) -> Self {
+        self.matching_rules
+            .insert(rule_id.as_ref().to_ascii_lowercase(), Arc::new(rule));
+        self
+    }
+
+    /// Set a matching rule for equality, approximate, ordering and substring filters on a given attribute
+    pub fn attribute_rule<S: AsRef<str>, R: MatchingRule + 'static>(mut self, attr: S, rule: R) -> Self {
+        self.attribute_rules
+            .insert(attr.as_ref().to_ascii_lowercase(), Arc::new(rule));
+        self
+    }
+
+    /// Return true if the entry matches the filter
+    pub fn evaluate(&self, filter: &Filter, entry: &SearchEntry) -> bool {
+        self.eval(filter, entry) == Some(true)
+    }
+
+    /// Parse a filter string and return true if the entry matches it
+    pub fn evaluate_str<S: AsRef<str>>(&self, filter: S, entry: &SearchEntry) -> Result<bool, Error> {
+        Ok(self.evaluate(&parse_filter(filter)?, entry))
+    }
+
+    fn rule_for(&self, attr: &str) -> &dyn MatchingRule {
+        let base = attr.split(';').next().unwrap_or_default().to_ascii_lowercase();
+        self.attribute_rules.get(&base).unwrap_or(&self.default_rule).as_ref()
+    }
+
+    fn values<'a>(&self, entry: &'a SearchEntry, attr: &'a str) -> impl Iterator<Item = &'a [u8]> + 'a {
+        entry
+            .attributes
+            .iter()
+            .filter(move |a| attr_matches(attr, &a.name))
+            .flat_map(|a| a.values.iter().map(|v| v.as_ref()))
+    }
+
+    // Three-valued evaluation as defined in RFC4511, `None` stands for undefined
+    fn eval(&self, filter: &Filter, entry: &SearchEntry) -> Option<bool> {
+        match filter {
+            Filter::And(filters) => {
+                let mut result = Some(true);
+                for f in filters.to_vec() {
+                    match self.eval(f, entry) {
+                        Some(false) => return Some(false),
+                        None => result = None,
+                        Some(true) => {}
+                    }
+                }
+                result
+            }
+            Filter::Or(filters) => {
+                let mut result = Some(false);
+                for f in filters.to_vec() {
+                    match self.eval(f, entry) {
+                        Some(true) => return Some(true),
+                        None => result = None,
+                        Some(false) => {}
+                    }
+                }
+                result
+            }
+            Filter::Not(filter) => self.eval(filter, entry).map(|r| !r),
+            Filter::EqualityMatch(ava) | Filter::ApproxMatch(ava) => {
+                let rule = self.rule_for(&ava.attribute_desc.0);
+                Some(self.any_value(entry, ava, |v| rule.matches(v, &ava.assertion_value)))
+            }
+            Filter::GreaterOrEqual(ava) => self.ordering(entry, ava, Ordering::is_ge),
+            Filter::LessOrEqual(ava) => self.ordering(entry, ava, Ordering::is_le),
+            Filter::Present(attr) => Some(entry.attributes.iter().any(|a| attr_matches(&attr.0, &a.name))),
+            Filter::Substrings(substrings) => self.substrings(entry, substrings),
+            Filter::ExtensibleMatch(mra) => self.extensible(entry, mra),
+            _ => None,
+        }
+    }
+
+    fn any_value<F>(&self, entry: &SearchEntry, ava: &AttributeValueAssertion, f: F) -> bool
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        self.values(entry, &ava.attribute_desc.0).any(f)
+    }
+
+    fn ordering<F>(&self, entry: &SearchEntry, ava: &AttributeValueAssertion, f: F) -> Option<bool>
+    where
+        F: Fn(Ordering) -> bool,
+    {
+        let rule = self.rule_for(&ava.attribute_desc.0);
+        let mut result = Some(false);
+        for value in self.values(entry, &ava.attribute_desc.0) {
+            match rule.compare(value, &ava.assertion_value) {
+                Some(ordering) if f(ordering) => return Some(true),
+                Some(_) => {}
+                None => result = None,
+            }
+        }
+        result
+    }
+
+    fn substrings(&self, entry: &SearchEntry, substrings: &SubstringFilter) -> Option<bool> {
+        let rule = self.rule_for(&substrings.r#type.0);
+        let mut choices = Vec::with_capacity(substrings.substrings.len());
+        for choice in &substrings.substrings {
+            choices.push(match choice {
+                SubstringChoice::Initial(v) => (SubstringKind::Initial, rule.substring_value(v)?),
+                SubstringChoice::Any(v) => (SubstringKind::Any, rule.substring_value(v)?),
+                SubstringChoice::Final(v) => (SubstringKind::Final, rule.substring_value(v)?),
+                _ => return None,
+            });
+        }
+
+        let mut result = Some(false);
+        for value in self.values(entry, &substrings.r#type.0) {
+            match rule.substring_value(value) {
+                Some(value) if substrings_match(&value, &choices) => return Some(true),
+                Some(_) => {}
+                None => result = None,
+            }
+        }
+        result
+    }
+
+    fn extensible(&self, entry: &SearchEntry, mra: &MatchingRuleAssertion) -> Option<bool> {
+        let rule = match mra.matching_rule {
+            Some(ref rule_id) => self.matching_rules.get(&rule_id.0.to_ascii_lowercase())?.as_ref(),
+            None => self.rule_for(mra.r#type.as_ref().map(|t| t.0.as_str())?),
+        };
+
+        let type_matches = |name: &str| mra.r#type.as_ref().is_none_or(|t| attr_matches(&t.0, name));
+
+        let found = entry
+            .attributes
+            .iter()
+            .filter(|a| type_matches(&a.name))
+            .flat_map(|a| a.values.iter())
+            .any(|v| rule.matches(v, &mra.match_value));
+
+        if found || !mra.dn_attributes {
+            return Some(found);
+        }
+
+        let dn = entry.parse_dn().ok()?;
+        Some(dn.rdns().iter().flat_map(|rdn| rdn.values()).any(|ava| {
+            type_matches(&ava.attr)
+                && match ava.value {
+                    AttributeValueKind::Text(ref value) => rule.matches(value.as_bytes(), &mra.match_value),
+                    AttributeValueKind::Ber(_) => false,
+                }
+        }))
+    }
+}
+
+/// Return true if the entry matches the filter, using the default [FilterEvaluator]
+pub fn matches_filter(filter: &Filter, entry: &SearchEntry) -> bool {
+    FilterEvaluator::new().evaluate(filter, entry)
+}
+
+impl SearchEntry {
+    /// Parse a filter string and return true if the entry matches it, using the default [FilterEvaluator]
+    pub fn matches<S: AsRef<str>>(&self, filter: S) -> Result<bool, Error> {
+        FilterEvaluator::new().evaluate_str(filter, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::model::Attribute;
+
+    fn entry() -> SearchEntry {
+        let attr = |name: &str, values: &[&str]| Attribute {
+            name: name.to_owned(),
+            values: values.iter().map(|v| Bytes::copy_from_slice(v.as_bytes())).collect(),
+        };
+        SearchEntry {
+            dn: "cn=Babs Jensen,ou=People,dc=example,dc=com".to_owned(),
+            attributes: vec![
+                attr("objectClass", &["top", "person"]),
+                attr("cn", &["Babs Jensen", "Barbara  Jensen"]),
+                attr("cn;lang-en", &["Babs"]),
+                attr("sn", &["Jensen"]),
+                attr("uidNumber", &["1005"]),
+                attr("userAccountControl", &["514"]),
+                attr("description", &["Manager of univ of michigan end"]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let entry = entry();
+        let cases = [
+            ("(cn=babs jensen)", true),
+            ("(CN=barbara jensen)", true),
+            ("(cn=Jensen)", false),
+            ("(cn;lang-en=babs)", true),
+            ("(cn;lang-de=babs)", false),
+            ("(mail=*)", false),
+            ("(SN=*)", true),
+            ("(!(mail=foo))", true),
+            ("(&(objectClass=person)(|(sn=Smith)(cn=Babs J*)))", true),
+            ("(description=*univ*of*mich*end)", true),
+            ("(description=manager*end*univ)", false),
+            ("(cn=*jen*)", true),
+            ("(uidNumber>=1000)", true),
+            ("(uidNumber<=999)", false),
+            ("(uidNumber>=999)", true),
+            ("(sn~=jensen)", true),
+            ("(userAccountControl:1.2.840.113556.1.4.803:=2)", true),
+            ("(userAccountControl:1.2.840.113556.1.4.803:=3)", false),
+            ("(userAccountControl:1.2.840.113556.1.4.804:=3)", true),
+            ("(sn:caseExactMatch:=jensen)", false),
+            ("(sn:2.5.13.5:=Jensen)", true),
+            ("(ou:dn:=people)", true),
+            ("(ou:=people)", false),
+            ("(:dn:2.5.13.2:=example)", true),
+            ("(cn:1.2.3.4:=x)", false),
+            ("(!(cn:1.2.3.4:=x))", false),
+        ];
+        for (filter, expected) in cases {
+            assert_eq!(entry.matches(filter).unwrap(), expected, "{filter}");
+        }
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        struct ReverseMatch;
+
+        impl MatchingRule for ReverseMatch {
+            fn matches(&self, value: &[u8], assertion: &[u8]) -> bool {
+                value.iter().rev().eq(assertion.iter())
+            }
+        }
+
+        let entry = entry();
+        let evaluator = FilterEvaluator::new()
+            .matching_rule("1.2.3.4", ReverseMatch)
+            .attribute_rule("sn", CaseExactMatch);
+
+        assert!(evaluator.evaluate_str("(sn:1.2.3.4:=nesneJ)", &entry).unwrap());
+        assert!(!evaluator.evaluate_str("(sn=jensen)", &entry).unwrap());
+        assert!(evaluator.evaluate_str("(sn=Jensen)", &entry).unwrap());
+        assert!(!evaluator.evaluate_str("(sn=je*)", &entry).unwrap());
+        assert!(evaluator.evaluate_str("(sn=Je*en)", &entry).unwrap());
+
+        // integer matching does not support substrings, the result is undefined
+        let evaluator = FilterEvaluator::new().attribute_rule("uidNumber", IntegerMatch);
+        assert!(!evaluator.evaluate_str("(uidNumber=10*)", &entry).unwrap());
+        assert!(!evaluator.evaluate_str("(!(uidNumber=10*))", &entry).unwrap());
+    }
+}
@@ -0,0 +1,162 @@
+//! Filter normalization
+
+use rasn_ldap::{AttributeValueAssertion, Filter, LdapString, MatchingRuleAssertion, SubstringFilter};
+
+use crate::filter::render_filter;
+
+fn lowercase(s: &LdapString) -> LdapString {
+    s.0.to_ascii_lowercase().into()
+}
+
+fn normalize_assertion(ava: AttributeValueAssertion) -> AttributeValueAssertion {
+    AttributeValueAssertion::new(lowercase(&ava.attribute_desc), ava.assertion_value)
+}
+
+#[allow(clippy::mutable_key_type)]
+fn normalize_set<F>(filters: Vec<Filter>, is_same: F) -> Vec<Filter>
+where
+    F: Fn(&Filter) -> Option<Vec<Filter>>,
+{
+    let mut flat = Vec::with_capacity(filters.len());
+    for filter in filters.into_iter().map(normalize_filter) {
+        match is_same(&filter) {
+            Some(children) => flat.extend(children),
+            None => flat.push(filter),
+        }
+    }
+    // filters without a string representation are kept in front and never deduplicated
+    let mut keyed = flat.into_iter().map(|f| (render_filter(&f), f)).collect::<Vec<_>>();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.dedup_by(|a, b| a.0.is_some() && a.0 == b.0);
+    keyed.into_iter().map(|(_, f)| f).collect()
+}
+
+fn and_children(filter: &Filter) -> Option<Vec<Filter>> {
+    match filter {
+        Filter::And(filters) => Some(filters.to_vec().into_iter().cloned().collect()),
+        _ => None,
+    }
+}
+
+fn or_children(filter: &Filter) -> Option<Vec<Filter>> {
+    match filter {
+        Filter::Or(filters) => Some(filters.to_vec().into_iter().cloned().collect()),
+        _ => None,
+    }
+}
+
+#[allow(clippy::mutable_key_type)]
+fn make_set(filters: Vec<Filter>, and: bool) -> Filter {
+    if filters.len() == 1 {
+        filters.into_iter().next().unwrap()
+    } else if and {
+        Filter::And(filters.into())
+    } else {
+        Filter::Or(filters.into())
+    }
+}
+
+// Apply De Morgan's law if it reduces the number of negations
+fn normalize_not(filter: Filter) -> Filter {
+    let (children, and) = match filter {
+        Filter::Not(inner) => return *inner,
+        Filter::And(ref filters) => (filters.to_vec(), true),
+        Filter::Or(ref filters) => (filters.to_vec(), false),
+        other => return Filter::Not(Box::new(other)),
+    };
+
+    let negated = children.iter().filter(|f| matches!(f, Filter::Not(_))).count();
+    if children.is_empty() || children.len() - negated > negated {
+        return Filter::Not(Box::new(filter));
+    }
+
+    let children = children.into_iter().cloned().map(normalize_not).collect::<Vec<_>>();
+    normalize_filter(make_set(children, !and))
+}
+
+/// Normalize a filter so that logically equivalent filters compare and hash equal.
+/// Nested AND and OR terms are flattened, duplicate terms are removed, double negations are eliminated,
+/// negations are pushed down with De Morgan's law when that reduces their number,
+/// attribute descriptions are lowercased and terms are sorted in the canonical order.
+pub fn normalize_filter(filter: Filter) -> Filter {
+    match filter {
+        Filter::And(filters) => make_set(
+            normalize_set(filters.to_vec().into_iter().cloned().collect(), and_children),
+            true,
+        ),
+        Filter::Or(filters) => make_set(
+            normalize_set(filters.to_vec().into_iter().cloned().collect(), or_children),
+            false,
+        ),
+        Filter::Not(inner) => normalize_not(normalize_filter(*inner)),
+        Filter::EqualityMatch(ava) => Filter::EqualityMatch(normalize_assertion(ava)),
+        Filter::GreaterOrEqual(ava) => Filter::GreaterOrEqual(normalize_assertion(ava)),
+        Filter::LessOrEqual(ava) => Filter::LessOrEqual(normalize_assertion(ava)),
+        Filter::ApproxMatch(ava) => Filter::ApproxMatch(normalize_assertion(ava)),
+        Filter::Present(attr) => Filter::Present(lowercase(&attr)),
+        Filter::Substrings(substrings) => Filter::Substrings(SubstringFilter::new(
+            lowercase(&substrings.r#type),
+            substrings.substrings,
+        )),
+        Filter::ExtensibleMatch(mra) => Filter::ExtensibleMatch(MatchingRuleAssertion::new(
+            mra.matching_rule.as_ref().map(lowercase),
+            mra.r#type.as_ref().map(lowercase),
+            mra.match_value,
+            mra.dn_attributes,
+        )),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
+
+    use super::*;
+    use crate::filter::parse_filter;
+
+    fn normalize(filter: &str) -> String {
+        render_filter(&normalize_filter(parse_filter(filter).unwrap())).unwrap()
+    }
+
+    fn hash(filter: &Filter) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        filter.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_normalize_filter() {
+        let cases = [
+            ("(&(cn=a)(&(sn=b)(&(uid=c))))", "(&(cn=a)(sn=b)(uid=c))"),
+            ("(|(sn=b)(cn=a)(SN=b))", "(|(cn=a)(sn=b))"),
+            ("(&(cn=a))", "(cn=a)"),
+            ("(!(!(cn=a)))", "(cn=a)"),
+            ("(!(&(!(cn=a))(!(sn=b))))", "(|(cn=a)(sn=b))"),
+            ("(!(|(!(cn=a))(!(sn=b))(uid=c)))", "(&(!(uid=c))(cn=a)(sn=b))"),
+            ("(!(&(cn=a)(sn=b)))", "(!(&(cn=a)(sn=b)))"),
+            (
+                "(&(objectClass=Person)(|(SN=Jensen)(cn=Babs J*)))",
+                "(&(objectclass=Person)(|(cn=Babs J*)(sn=Jensen)))",
+            ),
+            (
+                "(userAccountControl:1.2.840.113556.1.4.803:=2)",
+                "(useraccountcontrol:1.2.840.113556.1.4.803:=2)",
+            ),
+        ];
+        for (filter, expected) in cases {
+            assert_eq!(normalize(filter), expected, "{filter}");
+        }
+    }
+
+    #[test]
+    fn test_normalized_equality() {
+        let a = normalize_filter(parse_filter("(&(objectClass=person)(|(uid=a)(mail=b))(cn=*))").unwrap());
+        let b = normalize_filter(parse_filter("(&(CN=*)(&(|(MAIL=b)(uid=a)(uid=a)))(objectclass=person))").unwrap());
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+    }
+}
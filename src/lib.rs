@@ -8,6 +8,8 @@
 //! * SASL protection is not supported for plain connections, use TLS connection.
 //! * Channel binding is not supported.
 //!
+//! Server discovery from DNS SRV records requires the `dns-srv` feature flag.
+//!
 //! Usage example:
 //! ```no_run
 //! use futures::TryStreamExt;
@@ -47,19 +49,34 @@ pub use bytes;
 pub use rasn_ldap;
 
 pub use client::*;
+pub use dn::*;
+pub use extended::*;
 pub use model::*;
 pub use options::*;
 pub use request::*;
+pub use url::*;
 
 mod codec;
 mod conn;
-mod filter;
+#[cfg(test)]
+mod mock;
 
 pub mod channel;
 pub mod client;
 pub mod controls;
+pub mod dirsync;
+pub mod dn;
 pub mod error;
+pub mod extended;
+pub mod filter;
 pub mod model;
 pub mod oid;
 pub mod options;
+pub mod pool;
+pub mod psearch;
+pub mod reconnect;
+pub mod referral;
 pub mod request;
+pub mod resolver;
+pub mod sync;
+pub mod url;
@@ -0,0 +1,59 @@
+//! Mock LDAP server used by the tests
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use futures::{SinkExt, StreamExt};
+use rasn_ldap::{LdapMessage, LdapResult, ResultCode};
+use tokio::net::TcpListener;
+use tokio_util::codec::Framed;
+
+use crate::codec::LdapCodec;
+
+pub(crate) struct MockServer {
+    pub(crate) port: u16,
+    connections: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    // Start a server which passes every received message together with the zero-based connection number
+    // to the handler and sends back the returned replies
+    pub(crate) async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(usize, LdapMessage) -> Vec<LdapMessage> + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let handler = Arc::new(handler);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let connection = counter.fetch_add(1, Ordering::SeqCst);
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let mut framed = Framed::new(stream, LdapCodec);
+                    while let Some(Ok(msg)) = framed.next().await {
+                        for reply in handler(connection, msg) {
+                            if framed.send(reply).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        Self { port, connections }
+    }
+
+    // Number of accepted connections
+    pub(crate) fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+pub(crate) fn ldap_result(code: ResultCode) -> LdapResult {
+    LdapResult::new(code, "".into(), "".into())
+}
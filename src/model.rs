@@ -1,7 +1,9 @@
 //! Data structures
 
 use bytes::Bytes;
-pub use rasn_ldap::{AttributeValue, ResultCode, SearchRequestDerefAliases, SearchRequestScope};
+pub use rasn_ldap::{AttributeValue, Control, ResultCode, SearchRequestDerefAliases, SearchRequestScope};
+
+use crate::{dn::Dn, error::Error};
 
 /// LDAP attribute definition
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -63,6 +65,13 @@ pub struct SearchEntry {
     pub attributes: Attributes,
 }
 
+impl SearchEntry {
+    /// Parse the entry DN
+    pub fn parse_dn(&self) -> Result<Dn, Error> {
+        self.dn.parse()
+    }
+}
+
 impl From<rasn_ldap::SearchResultEntry> for SearchEntry {
     fn from(raw: rasn_ldap::SearchResultEntry) -> Self {
         SearchEntry {
@@ -71,3 +80,52 @@ impl From<rasn_ldap::SearchResultEntry> for SearchEntry {
         }
     }
 }
+
+/// An item returned by the search operation
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchItem {
+    /// Search entry
+    Entry(SearchEntry),
+    /// Search continuation reference with a list of LDAP URLs
+    Reference(Vec<String>),
+}
+
+/// The outcome of a successful LDAP operation
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OperationResult {
+    /// Result code
+    pub result_code: ResultCode,
+    /// Matched DN
+    pub matched_dn: String,
+    /// Diagnostic message
+    pub diagnostic_message: String,
+    /// Referral URLs
+    pub referrals: Vec<String>,
+    /// Response controls
+    pub controls: Vec<Control>,
+}
+
+impl OperationResult {
+    pub(crate) fn new(result: rasn_ldap::LdapResult, controls: Option<rasn_ldap::Controls>) -> Self {
+        OperationResult {
+            result_code: result.result_code,
+            matched_dn: result.matched_dn.0,
+            diagnostic_message: result.diagnostic_message.0,
+            referrals: result.referral.unwrap_or_default().into_iter().map(|r| r.0).collect(),
+            controls: controls.unwrap_or_default(),
+        }
+    }
+}
+
+/// A window of entries returned by the virtual list view search
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VirtualListView {
+    /// Entries in the window
+    pub entries: Vec<SearchEntry>,
+    /// Position of the target entry in the list, 1-based
+    pub target_position: u32,
+    /// Server estimate of the list size
+    pub content_count: u32,
+    /// Context id to pass in the next request
+    pub context_id: Option<Vec<u8>>,
+}
@@ -6,8 +6,44 @@ pub const STARTTLS_OID: &[u8] = b"1.3.6.1.4.1.1466.20037";
 /// WHOAMI extended operation
 pub const WHOAMI_OID: &[u8] = b"1.3.6.1.4.1.4203.1.11.3";
 
+/// Password modify extended operation
+pub const PASSWORD_MODIFY_OID: &[u8] = b"1.3.6.1.4.1.4203.1.11.1";
+
 /// Notice of disconnection response sent by the server
 pub const NOTICE_OF_DISCONNECTION_OID: &[u8] = b"1.3.6.1.4.1.1466.20036";
 
 /// SimplePagedResultsControl OID
 pub const SIMPLE_PAGED_RESULTS_CONTROL_OID: &[u8] = b"1.2.840.113556.1.4.319";
+
+/// ServerSideSortControl OID
+pub const SERVER_SIDE_SORT_CONTROL_OID: &[u8] = b"1.2.840.113556.1.4.473";
+
+/// SortResultControl OID
+pub const SORT_RESULT_CONTROL_OID: &[u8] = b"1.2.840.113556.1.4.474";
+
+/// VirtualListViewControl OID
+pub const VLV_REQUEST_CONTROL_OID: &[u8] = b"2.16.840.1.113730.3.4.9";
+
+/// VirtualListViewResponseControl OID
+pub const VLV_RESPONSE_CONTROL_OID: &[u8] = b"2.16.840.1.113730.3.4.10";
+
+/// SyncRequestControl OID
+pub const SYNC_REQUEST_CONTROL_OID: &[u8] = b"1.3.6.1.4.1.4203.1.9.1.1";
+
+/// SyncStateControl OID
+pub const SYNC_STATE_CONTROL_OID: &[u8] = b"1.3.6.1.4.1.4203.1.9.1.2";
+
+/// SyncDoneControl OID
+pub const SYNC_DONE_CONTROL_OID: &[u8] = b"1.3.6.1.4.1.4203.1.9.1.3";
+
+/// Sync info intermediate response OID
+pub const SYNC_INFO_OID: &[u8] = b"1.3.6.1.4.1.4203.1.9.1.4";
+
+/// PersistentSearchControl OID
+pub const PERSISTENT_SEARCH_CONTROL_OID: &[u8] = b"2.16.840.1.113730.3.4.3";
+
+/// EntryChangeNotificationControl OID
+pub const ENTRY_CHANGE_NOTIFICATION_CONTROL_OID: &[u8] = b"2.16.840.1.113730.3.4.7";
+
+/// DirSyncControl OID
+pub const DIRSYNC_CONTROL_OID: &[u8] = b"1.2.840.113556.1.4.841";
@@ -30,7 +30,7 @@ mod tls {
     }
 
     #[allow(clippy::large_enum_variant)]
-    #[derive(Debug)]
+    #[derive(Clone, Debug)]
     pub enum TlsBackend {
         #[cfg(feature = "tls-native-tls")]
         Native(TlsConnector),
@@ -56,7 +56,7 @@ mod tls {
     }
 
     /// TLS options
-    #[derive(Default, Debug)]
+    #[derive(Clone, Default, Debug)]
     pub struct TlsOptions {
         pub(crate) backend: Option<TlsBackend>,
         pub(crate) kind: TlsKind,
@@ -0,0 +1,462 @@
+//! Connection pool
+
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use parking_lot::Mutex;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    SearchRequest,
+    client::{BindCredentials, LdapClient, LdapClientBuilder, Result},
+};
+
+const DEFAULT_MAX_SIZE: usize = 10;
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(30);
+const MIN_MAINTENANCE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Health check performed on idle connections before they are handed out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HealthCheck {
+    /// Only check that the connection is not closed
+    #[default]
+    None,
+    /// Send the 'whoami' extended request
+    WhoAmI,
+    /// Query the root DSE object
+    RootDse,
+}
+
+struct IdleClient {
+    client: LdapClient,
+    since: Instant,
+}
+
+struct PoolInner {
+    builder: LdapClientBuilder,
+    credentials: Option<BindCredentials>,
+    min_size: usize,
+    max_size: usize,
+    idle_timeout: Option<Duration>,
+    health_check: HealthCheck,
+    idle: Mutex<VecDeque<IdleClient>>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl PoolInner {
+    fn size(&self) -> usize {
+        self.max_size - self.semaphore.available_permits() + self.idle.lock().len()
+    }
+
+    // Drop idle connections which are closed or expired, keeping at least `min_size` connections
+    fn prune(&self) {
+        let in_use = self.max_size - self.semaphore.available_permits();
+        let mut idle = self.idle.lock();
+        idle.retain(|c| !c.client.is_closed());
+        if let Some(timeout) = self.idle_timeout {
+            while in_use + idle.len() > self.min_size && idle.front().is_some_and(|c| c.since.elapsed() >= timeout) {
+                debug!("Closing idle pooled connection");
+                idle.pop_front();
+            }
+        }
+    }
+
+    // Open connections until the pool has at least `min_size` connections
+    async fn refill(&self) -> Result<()> {
+        while self.size() < self.min_size {
+            let client = self.new_client().await?;
+            self.idle.lock().push_back(IdleClient {
+                client,
+                since: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    // Close expired connections and replace lost ones in the background while the pool is alive
+    fn spawn_maintenance(self: &Arc<Self>) {
+        if self.min_size == 0 && self.idle_timeout.is_none() {
+            return;
+        }
+        let interval = self.idle_timeout.map_or(MAINTENANCE_INTERVAL, |t| {
+            t.clamp(MIN_MAINTENANCE_INTERVAL, MAINTENANCE_INTERVAL)
+        });
+        let pool = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(pool) = pool.upgrade() else {
+                    break;
+                };
+                pool.prune();
+                if let Err(e) = pool.refill().await {
+                    debug!("Failed to refill the pool: {e}");
+                }
+            }
+        });
+    }
+
+    async fn new_client(&self) -> Result<LdapClient> {
+        debug!("Opening new pooled connection");
+        let mut client = self.builder.clone().connect().await?;
+        if let Some(ref credentials) = self.credentials {
+            client.rebind(credentials.clone()).await?;
+        }
+        Ok(client)
+    }
+
+    async fn check(&self, client: &mut LdapClient) -> Result<()> {
+        if client.bind_credentials() != self.credentials {
+            debug!("Resetting bind state of the pooled connection");
+            client.reset_bind(self.credentials.clone()).await?;
+        }
+        match self.health_check {
+            HealthCheck::None => {}
+            HealthCheck::WhoAmI => {
+                client.whoami().await?;
+            }
+            HealthCheck::RootDse => {
+                client.search_one(SearchRequest::root_dse()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Connection pool builder
+pub struct LdapPoolBuilder {
+    builder: LdapClientBuilder,
+    credentials: Option<BindCredentials>,
+    min_size: usize,
+    max_size: usize,
+    idle_timeout: Option<Duration>,
+    health_check: HealthCheck,
+}
+
+impl LdapPoolBuilder {
+    /// Set the minimum number of connections kept open, default is 0.
+    /// Closed and detached connections are replaced in the background
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Set the maximum number of connections, default is 10
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Close connections which stay idle longer than a given timeout, default is no timeout.
+    /// Expired connections are closed in the background
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the health check performed on idle connections at checkout, default is [HealthCheck::None]
+    pub fn health_check(mut self, health_check: HealthCheck) -> Self {
+        self.health_check = health_check;
+        self
+    }
+
+    /// Bind every pooled connection with a given username and password
+    pub fn simple_bind<U, P>(mut self, username: U, password: P) -> Self
+    where
+        U: AsRef<str>,
+        P: AsRef<str>,
+    {
+        self.credentials = Some(BindCredentials::Simple {
+            username: username.as_ref().to_owned(),
+            password: password.as_ref().to_owned(),
+            controls: Vec::new(),
+        });
+        self
+    }
+
+    /// Bind every pooled connection with SASL EXTERNAL
+    pub fn sasl_external_bind(mut self) -> Self {
+        self.credentials = Some(BindCredentials::SaslExternal { controls: Vec::new() });
+        self
+    }
+
+    /// Create the pool and open the minimum number of connections
+    pub async fn build(self) -> Result<LdapPool> {
+        let max_size = self.max_size.max(self.min_size).max(1);
+        let inner = Arc::new(PoolInner {
+            builder: self.builder,
+            credentials: self.credentials,
+            min_size: self.min_size,
+            max_size,
+            idle_timeout: self.idle_timeout,
+            health_check: self.health_check,
+            idle: Mutex::new(VecDeque::new()),
+            semaphore: Arc::new(Semaphore::new(max_size)),
+        });
+
+        inner.refill().await?;
+        inner.spawn_maintenance();
+
+        Ok(LdapPool { inner })
+    }
+}
+
+/// A pool of LDAP connections. Each connection has its own bind state:
+/// a pooled client may bind as a different user, the pool identity is restored
+/// before the connection is handed out again. The default controls and timeouts
+/// of a returned client are reset to the client builder defaults.
+#[derive(Clone)]
+pub struct LdapPool {
+    inner: Arc<PoolInner>,
+}
+
+impl LdapPool {
+    /// Create a pool builder which uses a given client builder to open connections
+    pub fn builder(builder: LdapClientBuilder) -> LdapPoolBuilder {
+        LdapPoolBuilder {
+            builder,
+            credentials: None,
+            min_size: 0,
+            max_size: DEFAULT_MAX_SIZE,
+            idle_timeout: None,
+            health_check: HealthCheck::None,
+        }
+    }
+
+    /// Check out a connection, waiting until one is available if the pool is exhausted.
+    /// The connection is returned to the pool when the [PooledClient] is dropped
+    pub async fn get(&self) -> Result<PooledClient> {
+        let permit = self
+            .inner
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Pool semaphore closed");
+
+        loop {
+            self.inner.prune();
+            let idle = self.inner.idle.lock().pop_back();
+            let Some(IdleClient { mut client, .. }) = idle else {
+                break;
+            };
+            match self.inner.check(&mut client).await {
+                Ok(()) => return Ok(self.pooled(client, permit)),
+                Err(e) => debug!("Pooled connection failed the health check: {e}"),
+            }
+        }
+
+        let client = self.inner.new_client().await?;
+        Ok(self.pooled(client, permit))
+    }
+
+    fn pooled(&self, client: LdapClient, permit: OwnedSemaphorePermit) -> PooledClient {
+        PooledClient {
+            client: Some(client),
+            pool: self.inner.clone(),
+            _permit: permit,
+        }
+    }
+
+    /// Return the number of open connections, both idle and checked out
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Return the number of idle connections
+    pub fn idle(&self) -> usize {
+        self.inner.idle.lock().len()
+    }
+}
+
+/// A client checked out from the pool
+pub struct PooledClient {
+    client: Option<LdapClient>,
+    pool: Arc<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledClient {
+    /// Take the client out of the pool, it will not be returned on drop
+    pub fn detach(mut self) -> LdapClient {
+        self.client.take().expect("No pooled client")
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = LdapClient;
+
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().expect("No pooled client")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client.as_mut().expect("No pooled client")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        if let Some(mut client) = self.client.take()
+            && !client.is_closed()
+        {
+            client.reset_defaults(&self.pool.builder);
+            self.pool.idle.lock().push_back(IdleClient {
+                client,
+                since: Instant::now(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rasn_ldap::{AuthenticationChoice, BindResponse, Control, LdapMessage, ProtocolOp, ResultCode};
+
+    use super::*;
+    use crate::mock::MockServer;
+
+    // Minimal server which accepts any bind except the "bad" password and counts bind requests
+    async fn start_server(binds: Arc<AtomicUsize>) -> MockServer {
+        MockServer::start(move |_, msg| {
+            let ProtocolOp::BindRequest(req) = msg.protocol_op else {
+                return Vec::new();
+            };
+            binds.fetch_add(1, Ordering::SeqCst);
+            let code = match req.authentication {
+                AuthenticationChoice::Simple(ref password) if password.as_ref() == b"bad" => {
+                    ResultCode::InvalidCredentials
+                }
+                _ => ResultCode::Success,
+            };
+            let resp = BindResponse::new(code, "".into(), "".into(), None, None);
+            vec![LdapMessage::new(msg.message_id, ProtocolOp::BindResponse(resp))]
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_pool() {
+        let binds = Arc::new(AtomicUsize::new(0));
+        let server = start_server(binds.clone()).await;
+
+        let pool = LdapPool::builder(LdapClient::builder("127.0.0.1").port(server.port))
+            .min_size(1)
+            .max_size(2)
+            .simple_bind("cn=pool", "secret")
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(pool.size(), 1);
+        assert_eq!(binds.load(Ordering::SeqCst), 1);
+
+        let mut first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        assert_eq!(pool.size(), 2);
+        assert_eq!(server.connections(), 2);
+
+        first.simple_bind("cn=user", "password").await.unwrap();
+        assert_eq!(binds.load(Ordering::SeqCst), 3);
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle(), 2);
+
+        // Both connections are reused, the one with a changed identity is rebound
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        assert_eq!(server.connections(), 2);
+        assert_eq!(binds.load(Ordering::SeqCst), 4);
+
+        // The pool is exhausted
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), pool.get())
+                .await
+                .is_err()
+        );
+        drop(first);
+        drop(second.detach());
+        assert_eq!(pool.size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pool_failed_bind() {
+        let binds = Arc::new(AtomicUsize::new(0));
+        let server = start_server(binds.clone()).await;
+
+        let pool = LdapPool::builder(LdapClient::builder("127.0.0.1").port(server.port))
+            .max_size(1)
+            .simple_bind("cn=pool", "secret")
+            .build()
+            .await
+            .unwrap();
+
+        let mut client = pool.get().await.unwrap();
+        assert_eq!(binds.load(Ordering::SeqCst), 1);
+        assert!(client.simple_bind("cn=user", "bad").await.is_err());
+        drop(client);
+
+        // The failed bind left the connection anonymous, the pool identity is restored
+        let client = pool.get().await.unwrap();
+        assert_eq!(binds.load(Ordering::SeqCst), 3);
+        assert_eq!(server.connections(), 1);
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_pool_reset_defaults() {
+        let binds = Arc::new(AtomicUsize::new(0));
+        let server = start_server(binds).await;
+
+        let pool = LdapPool::builder(LdapClient::builder("127.0.0.1").port(server.port))
+            .max_size(1)
+            .build()
+            .await
+            .unwrap();
+
+        let mut client = pool.get().await.unwrap();
+        client.set_default_controls([Control::new(b"1.2.3.4".as_slice().into(), true, None)]);
+        drop(client);
+
+        let client = pool.get().await.unwrap();
+        assert!(client.default_controls().is_empty());
+        assert_eq!(pool.size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pool_maintenance() {
+        let binds = Arc::new(AtomicUsize::new(0));
+        let server = start_server(binds).await;
+
+        let pool = LdapPool::builder(LdapClient::builder("127.0.0.1").port(server.port))
+            .min_size(1)
+            .idle_timeout(Duration::from_millis(20))
+            .build()
+            .await
+            .unwrap();
+
+        // The expired connection above the minimum size is closed without a checkout
+        let first = pool.get().await.unwrap();
+        let second = pool.get().await.unwrap();
+        drop(first);
+        drop(second);
+        assert_eq!(pool.size(), 2);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(pool.size(), 1);
+
+        // The detached connection is replaced
+        drop(pool.get().await.unwrap().detach());
+        assert_eq!(pool.size(), 0);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(pool.size(), 1);
+        assert_eq!(pool.idle(), 1);
+    }
+}
@@ -0,0 +1,149 @@
+//! Persistent search (draft-ietf-ldapext-psearch)
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use rasn_ldap::{ProtocolOp, ResultCode};
+
+use crate::{
+    OperationResult, SearchEntry,
+    conn::{AbandonGuard, LdapConnection, MessageStream},
+    controls::{EntryChangeNotificationControl, ResponseControl},
+    error::Error,
+};
+
+/// An entry returned by the persistent search
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChangedEntry {
+    /// The changed entry
+    pub entry: SearchEntry,
+    /// Change notification, absent for the initial entries or if the notifications were not requested
+    pub change: Option<EntryChangeNotificationControl>,
+}
+
+/// A long-lived stream of changed entries.
+/// Dropping the stream abandons the persistent search.
+pub struct ChangedEntries {
+    inner: MessageStream,
+    guard: AbandonGuard,
+}
+
+impl ChangedEntries {
+    pub(crate) fn new(inner: MessageStream, connection: LdapConnection) -> Self {
+        Self {
+            guard: AbandonGuard::new(connection, inner.id()),
+            inner,
+        }
+    }
+
+    /// Return the message id of the search operation
+    pub fn message_id(&self) -> u32 {
+        self.inner.id()
+    }
+}
+
+impl Stream for ChangedEntries {
+    type Item = Result<ChangedEntry, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.guard.is_done() {
+            return Poll::Ready(None);
+        }
+        loop {
+            let rc = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(None) => {
+                    self.guard.set_done();
+                    if self.inner.is_abandoned() {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Ready(Some(Err(Error::ConnectionClosed)))
+                    }
+                }
+                Poll::Ready(Some(msg)) => match msg.protocol_op {
+                    ProtocolOp::SearchResEntry(item) => {
+                        let controls = msg.controls.unwrap_or_default();
+                        match EntryChangeNotificationControl::from_controls(&controls).transpose() {
+                            Ok(change) => Poll::Ready(Some(Ok(ChangedEntry {
+                                entry: item.into(),
+                                change,
+                            }))),
+                            Err(e) => Poll::Ready(Some(Err(e))),
+                        }
+                    }
+                    ProtocolOp::SearchResRef(_) => continue,
+                    ProtocolOp::SearchResDone(done) => {
+                        self.guard.set_done();
+                        if done.0.result_code == ResultCode::Success {
+                            Poll::Ready(None)
+                        } else {
+                            let result = OperationResult::new(done.0, msg.controls);
+                            Poll::Ready(Some(Err(Error::OperationFailed(result.into()))))
+                        }
+                    }
+                    _ => Poll::Ready(Some(Err(Error::InvalidResponse))),
+                },
+            };
+            return rc;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use rasn_ldap::{Control, LdapMessage, SearchResultEntry};
+
+    use super::*;
+    use crate::{
+        LdapClient, SearchRequest,
+        controls::{ChangeType, PersistentSearchControl},
+        mock::MockServer,
+    };
+
+    #[tokio::test]
+    async fn test_persistent_search() {
+        // Server which returns an initial entry and a changed one, keeping the search open
+        let server = MockServer::start(|_, msg| {
+            let ProtocolOp::SearchRequest(_) = msg.protocol_op else {
+                return Vec::new();
+            };
+            let entry = |dn: &str| ProtocolOp::SearchResEntry(SearchResultEntry::new(dn.into(), Vec::new()));
+            let change = Control::new(
+                EntryChangeNotificationControl::OID.into(),
+                false,
+                Some(b"\x30\x03\x0a\x01\x01".as_slice().into()),
+            );
+            let mut changed = LdapMessage::new(msg.message_id, entry("cn=changed"));
+            changed.controls = Some(vec![change]);
+            vec![LdapMessage::new(msg.message_id, entry("cn=initial")), changed]
+        })
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let mut entries = client
+            .persistent_search(
+                SearchRequest::root_dse(),
+                PersistentSearchControl::new().changes_only(false),
+            )
+            .await
+            .unwrap();
+
+        let initial = entries.next().await.unwrap().unwrap();
+        assert_eq!(initial.entry.dn, "cn=initial");
+        assert!(initial.change.is_none());
+
+        let changed = entries.next().await.unwrap().unwrap();
+        assert_eq!(changed.entry.dn, "cn=changed");
+        let change = changed.change.unwrap();
+        assert_eq!(change.change_type, ChangeType::Add);
+        assert_eq!(change.previous_dn, None);
+    }
+}
@@ -0,0 +1,316 @@
+//! Reconnecting client
+
+use std::{future::Future, time::Duration};
+
+use futures::TryStreamExt;
+use log::debug;
+
+use crate::{
+    SearchEntry, SearchRequest,
+    client::{BindCredentials, LdapClient, LdapClientBuilder, Result},
+    error::Error,
+};
+
+/// Retry policy for reconnects and idempotent operations, with exponential backoff
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a default retry policy: 3 retries, backoff from 100ms up to 10s
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of retries, 0 disables retrying
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry, it is doubled for every next one
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the maximum delay between retries
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+// Errors caused by a lost connection, which can be fixed by reconnecting
+fn is_connection_error(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::Io(_) | Error::Channel(_) | Error::Send(_) | Error::ConnectionClosed
+    )
+}
+
+// Errors after which an idempotent operation is retried on a new connection
+fn is_retryable(e: &Error) -> bool {
+    is_connection_error(e) || matches!(e, Error::Timeout)
+}
+
+/// A client which reconnects when the connection is lost, including a notice of disconnection.
+/// After reconnecting, the last successful bind is replayed together with its request controls; STARTTLS is negotiated again
+/// if it is configured in the client builder. Idempotent operations are retried according to the [RetryPolicy].
+pub struct ReconnectingClient {
+    builder: LdapClientBuilder,
+    policy: RetryPolicy,
+    client: Option<LdapClient>,
+    credentials: Option<BindCredentials>,
+}
+
+impl ReconnectingClient {
+    /// Create a reconnecting client and connect to the server
+    pub async fn connect(builder: LdapClientBuilder, policy: RetryPolicy) -> Result<Self> {
+        let mut client = Self {
+            builder,
+            policy,
+            client: None,
+            credentials: None,
+        };
+        client.reconnect().await?;
+        Ok(client)
+    }
+
+    async fn try_connect(&self) -> Result<LdapClient> {
+        let mut client = self.builder.clone().connect().await?;
+        if let Some(ref credentials) = self.credentials {
+            client.rebind(credentials.clone()).await?;
+        }
+        Ok(client)
+    }
+
+    // Drop the current connection, keeping its bind credentials for the next one
+    fn disconnect(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.credentials = client.bind_credentials();
+        }
+    }
+
+    // Return the current client, making a single connection attempt if it is lost
+    async fn connected(&mut self) -> Result<LdapClient> {
+        if self.client.as_ref().is_none_or(|c| c.is_closed()) {
+            self.disconnect();
+            self.client = Some(self.try_connect().await?);
+        }
+        Ok(self.client.clone().expect("No client"))
+    }
+
+    async fn reconnect(&mut self) -> Result<()> {
+        self.disconnect();
+        let mut attempt = 0;
+        loop {
+            match self.try_connect().await {
+                Ok(client) => {
+                    self.client = Some(client);
+                    return Ok(());
+                }
+                Err(e) if is_connection_error(&e) && attempt < self.policy.max_retries => {
+                    let backoff = self.policy.backoff(attempt);
+                    debug!("Reconnect failed: {e}, retrying in {backoff:?}");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Return a connected client, reconnecting if the connection is lost.
+    /// Operations made with the returned client are not retried
+    pub async fn client(&mut self) -> Result<&mut LdapClient> {
+        if self.client.as_ref().is_none_or(|c| c.is_closed()) {
+            debug!("Connection lost, reconnecting");
+            self.reconnect().await?;
+        }
+        Ok(self.client.as_mut().expect("No client"))
+    }
+
+    /// Run an idempotent operation, reconnecting and retrying it on connection errors and timeouts.
+    /// Reconnects and operation retries share the same attempt count and backoff
+    pub async fn retry<T, F, Fut>(&mut self, mut op: F) -> Result<T>
+    where
+        F: FnMut(LdapClient) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = match self.connected().await {
+                Ok(client) => op(client).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if is_retryable(&e) && attempt < self.policy.max_retries => {
+                    let backoff = self.policy.backoff(attempt);
+                    debug!("Operation failed: {e}, retrying in {backoff:?}");
+                    self.disconnect();
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Perform search operation and collect all entries, retrying on connection errors and timeouts
+    pub async fn search(&mut self, request: SearchRequest) -> Result<Vec<SearchEntry>> {
+        self.retry(|mut client| {
+            let request = request.clone();
+            async move { client.search(request).await?.try_collect().await }
+        })
+        .await
+    }
+
+    /// Perform search operation and return one result, retrying on connection errors and timeouts
+    pub async fn search_one(&mut self, request: SearchRequest) -> Result<Option<SearchEntry>> {
+        self.retry(|mut client| {
+            let request = request.clone();
+            async move { client.search_one(request).await }
+        })
+        .await
+    }
+
+    /// Perform compare operation, retrying on connection errors and timeouts
+    pub async fn compare<S, A, V>(&mut self, dn: S, attribute: A, value: V) -> Result<bool>
+    where
+        S: AsRef<str>,
+        A: AsRef<str>,
+        V: AsRef<[u8]>,
+    {
+        let (dn, attribute, value) = (dn.as_ref(), attribute.as_ref(), value.as_ref());
+        self.retry(|mut client| async move { client.compare(dn, attribute, value).await })
+            .await
+    }
+
+    /// Send 'whoami' extended request, retrying on connection errors and timeouts
+    pub async fn whoami(&mut self) -> Result<Option<String>> {
+        self.retry(|mut client| async move { client.whoami().await }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use rasn_ldap::{BindResponse, CompareResponse, Control, ExtendedResponse, LdapMessage, ProtocolOp, ResultCode};
+
+    use super::*;
+    use crate::{
+        OperationOptions,
+        mock::{MockServer, ldap_result},
+        oid,
+    };
+
+    // The first connection sends a notice of disconnection on compare, the next ones answer it.
+    // Bind requests which carry request controls are counted
+    async fn start_server(binds: Arc<AtomicUsize>) -> MockServer {
+        MockServer::start(move |connection, msg| {
+            let op = match msg.protocol_op {
+                ProtocolOp::BindRequest(_) => {
+                    if msg.controls.is_some_and(|c| !c.is_empty()) {
+                        binds.fetch_add(1, Ordering::SeqCst);
+                    }
+                    ProtocolOp::BindResponse(BindResponse::new(ResultCode::Success, "".into(), "".into(), None, None))
+                }
+                ProtocolOp::CompareRequest(_) if connection == 0 => {
+                    let notice = ExtendedResponse {
+                        result_code: ResultCode::Unavailable,
+                        matched_dn: "".into(),
+                        diagnostic_message: "".into(),
+                        referral: None,
+                        response_name: Some(oid::NOTICE_OF_DISCONNECTION_OID.into()),
+                        response_value: None,
+                    };
+                    return vec![LdapMessage::new(0, ProtocolOp::ExtendedResp(notice))];
+                }
+                ProtocolOp::CompareRequest(_) => {
+                    ProtocolOp::CompareResponse(CompareResponse(ldap_result(ResultCode::CompareTrue)))
+                }
+                _ => return Vec::new(),
+            };
+            vec![LdapMessage::new(msg.message_id, op)]
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let binds = Arc::new(AtomicUsize::new(0));
+        let server = start_server(binds.clone()).await;
+
+        let policy = RetryPolicy::new().initial_backoff(Duration::from_millis(10));
+        let mut client = ReconnectingClient::connect(LdapClient::builder("127.0.0.1").port(server.port), policy)
+            .await
+            .unwrap();
+        client
+            .client()
+            .await
+            .unwrap()
+            .simple_bind_with_options(
+                "cn=user",
+                "password",
+                OperationOptions::new().control(Control::new(b"1.2.3.4".as_slice().into(), false, None)),
+            )
+            .await
+            .unwrap();
+
+        assert!(client.compare("cn=user", "cn", "user").await.unwrap());
+        assert_eq!(server.connections(), 2);
+        assert_eq!(binds.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempts() {
+        // Server which never answers
+        let server = MockServer::start(|_, _| Vec::new()).await;
+
+        let builder = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .operation_timeout(Duration::from_millis(20));
+        let policy = RetryPolicy::new()
+            .max_retries(2)
+            .initial_backoff(Duration::from_millis(1));
+        let mut client = ReconnectingClient::connect(builder, policy).await.unwrap();
+
+        assert!(matches!(client.whoami().await, Err(Error::Timeout)));
+        assert_eq!(server.connections(), 3);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(5));
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(4));
+        assert_eq!(policy.backoff(3), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+}
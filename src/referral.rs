@@ -0,0 +1,242 @@
+//! Referral chasing
+
+use std::{
+    collections::{HashSet, VecDeque},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Future, Stream, future::BoxFuture};
+use log::debug;
+use rasn_ldap::ResultCode;
+
+use crate::{
+    LdapClient, SearchItem, SearchItems,
+    error::Error,
+    filter::parse_filter,
+    model::SearchEntry,
+    options::{TlsKind, TlsOptions},
+    request::SearchRequest,
+    url::{LdapUrl, LdapUrlScheme},
+};
+
+#[allow(clippy::large_enum_variant)]
+enum State {
+    Streaming(SearchItems, u32),
+    Connecting(BoxFuture<'static, Result<SearchItems, Error>>, u32),
+    Idle,
+}
+
+/// A stream of search entries which follows search continuation references and referrals
+/// by opening follow-up connections. The follow-up connections reuse the TLS options
+/// and the bind credentials of the original client.
+pub struct ReferralEntries {
+    client: LdapClient,
+    request: SearchRequest,
+    max_hops: u32,
+    pending: VecDeque<(String, u32)>,
+    visited: HashSet<String>,
+    state: State,
+}
+
+// The referral scheme selects the transport: ldaps uses TLS, ldap keeps the STARTTLS or plain
+// connection of the client. A TLS client negotiates STARTTLS to keep the follow-up connection protected
+fn referral_tls_kind(client: TlsKind, scheme: LdapUrlScheme) -> TlsKind {
+    match (scheme, client) {
+        (LdapUrlScheme::Ldaps, _) => TlsKind::Tls,
+        (_, TlsKind::Tls) => TlsKind::StartTls,
+        (_, kind) => kind,
+    }
+}
+
+impl ReferralEntries {
+    pub(crate) fn new(client: LdapClient, request: SearchRequest, items: SearchItems, max_hops: u32) -> Self {
+        Self {
+            client,
+            request,
+            max_hops,
+            pending: VecDeque::new(),
+            visited: HashSet::new(),
+            state: State::Streaming(items, 0),
+        }
+    }
+
+    fn add_referrals(&mut self, urls: Vec<String>, hops: u32) {
+        if hops >= self.max_hops {
+            debug!("Referral hop limit reached, ignoring referrals: {urls:?}");
+            return;
+        }
+        for url in urls {
+            if self.visited.insert(url.clone()) {
+                self.pending.push_back((url, hops + 1));
+            }
+        }
+    }
+
+    fn follow(&self, url: &str) -> Result<BoxFuture<'static, Result<SearchItems, Error>>, Error> {
+        let referral = url.parse::<LdapUrl>()?;
+        let (Some(host), Some(port)) = (referral.host.clone(), referral.port_or_default()) else {
+            return Err(Error::InvalidUrl(url.to_owned()));
+        };
+
+        debug!("Following referral: {url}");
+
+        let mut tls_options = TlsOptions::clone(self.client.tls_options());
+        tls_options.domain_name = None;
+        tls_options.kind = referral_tls_kind(tls_options.kind, referral.scheme);
+
+        let default_controls = self.client.default_controls().to_vec();
+        let credentials = self.client.bind_credentials();
+        let timeouts = self.client.timeouts();
+
+        let mut request = self.request.clone();
+        if !referral.base_dn.is_empty() {
+            request.inner.base_object = referral.base_dn.into();
+        }
+        if let Some(scope) = referral.scope {
+            request.inner.scope = scope;
+        }
+        // a filter in the URL replaces the original one (RFC4511 4.5.3)
+        if let Some(ref filter) = referral.filter {
+            request.inner.filter = parse_filter(filter)?;
+        }
+
+        Ok(Box::pin(async move {
+            let mut client = LdapClient::connect(host, port, tls_options, timeouts).await?;
+            client.set_default_controls(default_controls);
+            if let Some(credentials) = credentials {
+                client.rebind(credentials).await?;
+            }
+            Ok(client.search(request).await?.items())
+        }))
+    }
+}
+
+impl Stream for ReferralEntries {
+    type Item = Result<SearchEntry, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.state {
+                State::Idle => match self.pending.pop_front() {
+                    None => return Poll::Ready(None),
+                    Some((url, hops)) => match self.follow(&url) {
+                        Ok(fut) => self.state = State::Connecting(fut, hops),
+                        Err(err) => return Poll::Ready(Some(Err(err))),
+                    },
+                },
+                State::Connecting(ref mut fut, hops) => match Pin::new(fut).poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(items)) => self.state = State::Streaming(items, hops),
+                    Poll::Ready(Err(err)) => {
+                        self.state = State::Idle;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                },
+                State::Streaming(ref mut items, hops) => match Pin::new(items).poll_next(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(SearchItem::Entry(entry)))) => return Poll::Ready(Some(Ok(entry))),
+                    Poll::Ready(Some(Ok(SearchItem::Reference(urls)))) => self.add_referrals(urls, hops),
+                    Poll::Ready(Some(Err(Error::OperationFailed(e)))) if e.result_code == ResultCode::Referral => {
+                        self.state = State::Idle;
+                        self.add_referrals(e.referrals, hops);
+                    }
+                    Poll::Ready(Some(Err(err))) => {
+                        self.state = State::Idle;
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                    Poll::Ready(None) => self.state = State::Idle,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use rasn_ldap::{LdapMessage, ProtocolOp, SearchResultDone, SearchResultEntry, SearchResultReference};
+
+    use super::*;
+    use crate::{
+        Attribute,
+        filter::render_filter,
+        mock::{MockServer, ldap_result},
+    };
+
+    // Server which returns one entry named after the search base with the search filter as an attribute,
+    // followed by the given references
+    async fn start_server(references: Vec<String>) -> u16 {
+        let server = MockServer::start(move |_, msg| {
+            let ProtocolOp::SearchRequest(req) = msg.protocol_op else {
+                return Vec::new();
+            };
+            let filter = Attribute {
+                name: "filter".to_owned(),
+                values: vec![render_filter(&req.filter).unwrap().into()],
+            };
+            let entry = SearchResultEntry::new(req.base_object.clone(), vec![filter.into()]);
+            let mut replies = vec![ProtocolOp::SearchResEntry(entry)];
+            if !references.is_empty() {
+                let refs = references.iter().map(|r| r.as_str().into()).collect();
+                replies.push(ProtocolOp::SearchResRef(SearchResultReference(refs)));
+            }
+            replies.push(ProtocolOp::SearchResDone(SearchResultDone(ldap_result(
+                ResultCode::Success,
+            ))));
+            replies
+                .into_iter()
+                .map(|op| LdapMessage::new(msg.message_id, op))
+                .collect()
+        })
+        .await;
+        server.port
+    }
+
+    #[tokio::test]
+    async fn test_search_referrals() {
+        let referred = start_server(Vec::new()).await;
+        let port = start_server(vec![
+            format!("ldap://127.0.0.1:{referred}/dc=other"),
+            format!("ldap://127.0.0.1:{referred}/dc=filtered??sub?(cn=foo)"),
+        ])
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1").port(port).connect().await.unwrap();
+        let request = SearchRequest::builder()
+            .base_dn("dc=example")
+            .filter("(objectClass=*)")
+            .build()
+            .unwrap();
+        let entries = client
+            .search_referrals(request, 1)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let results = entries
+            .into_iter()
+            .map(|e| (e.dn, e.attributes[0].values[0].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            [
+                ("dc=example".to_owned(), "(objectClass=*)".into()),
+                ("dc=other".to_owned(), "(objectClass=*)".into()),
+                ("dc=filtered".to_owned(), "(cn=foo)".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_referral_tls_kind() {
+        assert_eq!(referral_tls_kind(TlsKind::Tls, LdapUrlScheme::Ldap), TlsKind::StartTls);
+        assert_eq!(referral_tls_kind(TlsKind::Plain, LdapUrlScheme::Ldap), TlsKind::Plain);
+        assert_eq!(
+            referral_tls_kind(TlsKind::StartTls, LdapUrlScheme::Ldap),
+            TlsKind::StartTls
+        );
+        assert_eq!(referral_tls_kind(TlsKind::Plain, LdapUrlScheme::Ldaps), TlsKind::Tls);
+    }
+}
@@ -2,15 +2,21 @@
 
 use std::time::Duration;
 
-use rasn_ldap::{ChangeOperation, ModifyRequestChanges};
+use rasn_ldap::{ChangeOperation, Control, Filter, ModifyRequestChanges};
 
 use crate::{
     Attribute,
     error::Error,
-    filter::parse_filter,
+    filter::{parse_filter, render_filter},
     model::{SearchRequestDerefAliases, SearchRequestScope},
 };
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum SearchFilter {
+    Text(String),
+    Typed(Filter),
+}
+
 /// LDAP search request builder
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SearchRequestBuilder {
@@ -20,8 +26,10 @@ pub struct SearchRequestBuilder {
     size_limit: u32,
     time_limit: Duration,
     types_only: bool,
-    filter: String,
+    filter: SearchFilter,
     attributes: Vec<String>,
+    controls: Vec<Control>,
+    timeout: Option<Duration>,
 }
 
 impl SearchRequestBuilder {
@@ -33,8 +41,10 @@ impl SearchRequestBuilder {
             size_limit: 0,
             time_limit: Duration::default(),
             types_only: false,
-            filter: Default::default(),
+            filter: SearchFilter::Text(Default::default()),
             attributes: Vec::new(),
+            controls: Vec::new(),
+            timeout: None,
         }
     }
 
@@ -76,7 +86,13 @@ impl SearchRequestBuilder {
 
     /// Set a search filter
     pub fn filter<S: AsRef<str>>(mut self, filter: S) -> Self {
-        filter.as_ref().clone_into(&mut self.filter);
+        self.filter = SearchFilter::Text(filter.as_ref().to_owned());
+        self
+    }
+
+    /// Set a search filter created with the [filter](crate::filter) builder functions
+    pub fn filter_expr(mut self, filter: Filter) -> Self {
+        self.filter = SearchFilter::Typed(filter);
         self
     }
 
@@ -100,24 +116,48 @@ impl SearchRequestBuilder {
         self
     }
 
+    /// Add a control to send with the request
+    pub fn control(mut self, control: Control) -> Self {
+        self.controls.push(control);
+        self
+    }
+
+    /// Set the maximum time to wait for each response, overriding the client default operation timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Create a search request
     pub fn build(self) -> Result<SearchRequest, Error> {
-        Ok(SearchRequest(rasn_ldap::SearchRequest::new(
-            self.base_dn.into(),
-            self.scope,
-            self.deref_aliases,
-            self.size_limit,
-            self.time_limit.as_secs() as u32,
-            self.types_only,
-            parse_filter(self.filter)?,
-            self.attributes.into_iter().map(Into::into).collect(),
-        )))
+        let filter = match self.filter {
+            SearchFilter::Text(filter) => parse_filter(filter)?,
+            SearchFilter::Typed(filter) => filter,
+        };
+        Ok(SearchRequest {
+            inner: rasn_ldap::SearchRequest::new(
+                self.base_dn.into(),
+                self.scope,
+                self.deref_aliases,
+                self.size_limit,
+                self.time_limit.as_secs() as u32,
+                self.types_only,
+                filter,
+                self.attributes.into_iter().map(Into::into).collect(),
+            ),
+            controls: self.controls,
+            timeout: self.timeout,
+        })
     }
 }
 
 /// Search request
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct SearchRequest(pub(crate) rasn_ldap::SearchRequest);
+pub struct SearchRequest {
+    pub(crate) inner: rasn_ldap::SearchRequest,
+    pub(crate) controls: Vec<Control>,
+    pub(crate) timeout: Option<Duration>,
+}
 
 impl SearchRequest {
     /// Create search request  builder
@@ -129,17 +169,26 @@ impl SearchRequest {
     pub fn root_dse() -> Self {
         Self::builder().filter("(objectClass=*)").build().unwrap()
     }
+
+    /// Return the search filter in its string representation, if it has one
+    pub fn filter_string(&self) -> Option<String> {
+        render_filter(&self.inner.filter)
+    }
 }
 
 impl From<SearchRequest> for rasn_ldap::SearchRequest {
     fn from(req: SearchRequest) -> Self {
-        req.0
+        req.inner
     }
 }
 
-/// Search request
+/// Modify request
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct ModifyRequest(pub(crate) rasn_ldap::ModifyRequest);
+pub struct ModifyRequest {
+    pub(crate) inner: rasn_ldap::ModifyRequest,
+    pub(crate) controls: Vec<Control>,
+    pub(crate) timeout: Option<Duration>,
+}
 
 impl ModifyRequest {
     /// Create a modification request builder for a given object DN
@@ -150,7 +199,7 @@ impl ModifyRequest {
 
 impl From<ModifyRequest> for rasn_ldap::ModifyRequest {
     fn from(req: ModifyRequest) -> Self {
-        req.0
+        req.inner
     }
 }
 
@@ -159,6 +208,8 @@ impl From<ModifyRequest> for rasn_ldap::ModifyRequest {
 pub struct ModifyRequestBuilder {
     object: String,
     operations: Vec<(ChangeOperation, Attribute)>,
+    controls: Vec<Control>,
+    timeout: Option<Duration>,
 }
 
 impl ModifyRequestBuilder {
@@ -166,6 +217,8 @@ impl ModifyRequestBuilder {
         Self {
             object: object.as_ref().to_owned(),
             operations: Vec::new(),
+            controls: Vec::new(),
+            timeout: None,
         }
     }
 
@@ -187,6 +240,18 @@ impl ModifyRequestBuilder {
         self
     }
 
+    /// Add a control to send with the request
+    pub fn control(mut self, control: Control) -> Self {
+        self.controls.push(control);
+        self
+    }
+
+    /// Set the operation timeout, overriding the client default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Build the modification request
     pub fn build(self) -> ModifyRequest {
         let req = rasn_ldap::ModifyRequest {
@@ -200,6 +265,127 @@ impl ModifyRequestBuilder {
                 })
                 .collect(),
         };
-        ModifyRequest(req)
+        ModifyRequest {
+            inner: req,
+            controls: self.controls,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Modify DN request
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModifyDnRequest {
+    pub(crate) inner: rasn_ldap::ModifyDnRequest,
+    pub(crate) controls: Vec<Control>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl ModifyDnRequest {
+    /// Create a modify DN request builder for a given object DN and a new RDN
+    pub fn builder<S, R>(object: S, new_rdn: R) -> ModifyDnRequestBuilder
+    where
+        S: AsRef<str>,
+        R: AsRef<str>,
+    {
+        ModifyDnRequestBuilder::new(object, new_rdn)
+    }
+}
+
+impl From<ModifyDnRequest> for rasn_ldap::ModifyDnRequest {
+    fn from(req: ModifyDnRequest) -> Self {
+        req.inner
+    }
+}
+
+/// LDAP modify DN request builder
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModifyDnRequestBuilder {
+    object: String,
+    new_rdn: String,
+    delete_old_rdn: bool,
+    new_superior: Option<String>,
+    controls: Vec<Control>,
+    timeout: Option<Duration>,
+}
+
+impl ModifyDnRequestBuilder {
+    pub(crate) fn new<S, R>(object: S, new_rdn: R) -> Self
+    where
+        S: AsRef<str>,
+        R: AsRef<str>,
+    {
+        Self {
+            object: object.as_ref().to_owned(),
+            new_rdn: new_rdn.as_ref().to_owned(),
+            delete_old_rdn: true,
+            new_superior: None,
+            controls: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Set a flag indicating whether the old RDN values should be deleted from the entry, default is true
+    pub fn delete_old_rdn(mut self, delete_old_rdn: bool) -> Self {
+        self.delete_old_rdn = delete_old_rdn;
+        self
+    }
+
+    /// Move the entry under a new parent DN
+    pub fn new_superior<S: AsRef<str>>(mut self, new_superior: S) -> Self {
+        self.new_superior = Some(new_superior.as_ref().to_owned());
+        self
+    }
+
+    /// Add a control to send with the request
+    pub fn control(mut self, control: Control) -> Self {
+        self.controls.push(control);
+        self
+    }
+
+    /// Set the operation timeout, overriding the client default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Build the modify DN request
+    pub fn build(self) -> ModifyDnRequest {
+        ModifyDnRequest {
+            inner: rasn_ldap::ModifyDnRequest {
+                entry: self.object.into(),
+                new_rdn: self.new_rdn.into(),
+                delete_old_rdn: self.delete_old_rdn,
+                new_superior: self.new_superior.map(Into::into),
+            },
+            controls: self.controls,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Additional options for the operations which do not have a dedicated request type
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OperationOptions {
+    pub(crate) controls: Vec<Control>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl OperationOptions {
+    /// Create default operation options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a control to send with the request
+    pub fn control(mut self, control: Control) -> Self {
+        self.controls.push(control);
+        self
+    }
+
+    /// Set the operation timeout, overriding the client default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
     }
 }
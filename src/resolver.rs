@@ -0,0 +1,149 @@
+//! Server discovery with DNS SRV records (RFC2782)
+
+use std::io;
+
+use futures::future::BoxFuture;
+
+use crate::error::Error;
+
+/// DNS SRV record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SrvRecord {
+    /// Record priority, lower values are preferred
+    pub priority: u16,
+    /// Relative weight among the records with the same priority
+    pub weight: u16,
+    /// Server port
+    pub port: u16,
+    /// Server host name
+    pub target: String,
+}
+
+impl SrvRecord {
+    /// Create a new SRV record
+    pub fn new<S: AsRef<str>>(priority: u16, weight: u16, port: u16, target: S) -> Self {
+        Self {
+            priority,
+            weight,
+            port,
+            target: target.as_ref().to_owned(),
+        }
+    }
+}
+
+/// Pluggable name resolver used for the server discovery
+pub trait Resolver: Send + Sync {
+    /// Look up the SRV records for a given name, such as `_ldap._tcp.example.com`
+    fn lookup_srv<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<SrvRecord>, Error>>;
+}
+
+/// System DNS resolver. SRV lookups require the `dns-srv` feature
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    #[cfg(feature = "dns-srv")]
+    fn lookup_srv<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<SrvRecord>, Error>> {
+        Box::pin(async move {
+            let resolver = hickory_resolver::TokioResolver::builder_tokio()
+                .map_err(io::Error::other)?
+                .build();
+            let lookup = resolver.srv_lookup(name).await.map_err(io::Error::other)?;
+            Ok(lookup
+                .iter()
+                .map(|srv| SrvRecord::new(srv.priority(), srv.weight(), srv.port(), srv.target().to_utf8()))
+                .collect())
+        })
+    }
+
+    #[cfg(not(feature = "dns-srv"))]
+    fn lookup_srv<'a>(&'a self, _name: &'a str) -> BoxFuture<'a, Result<Vec<SrvRecord>, Error>> {
+        Box::pin(async {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "DNS SRV lookup requires the dns-srv feature",
+            )
+            .into())
+        })
+    }
+}
+
+// RFC2782 ordering: ascending priority, weighted random selection within the same priority.
+// `random(n)` returns a number in the range 0..=n
+fn order_srv_records<F>(mut records: Vec<SrvRecord>, mut random: F) -> Vec<SrvRecord>
+where
+    F: FnMut(u32) -> u32,
+{
+    for record in &mut records {
+        record.target = record.target.trim_end_matches('.').to_owned();
+    }
+    // an empty target means the service is not available
+    records.retain(|r| !r.target.is_empty());
+    records.sort_by_key(|r| (r.priority, r.weight != 0));
+
+    let mut result = Vec::with_capacity(records.len());
+    while !records.is_empty() {
+        let priority = records[0].priority;
+        let count = records.iter().take_while(|r| r.priority == priority).count();
+        let mut group = records.drain(..count).collect::<Vec<_>>();
+        while !group.is_empty() {
+            let total = group.iter().map(|r| r.weight as u32).sum::<u32>();
+            let selected = random(total);
+            let mut sum = 0;
+            let index = group
+                .iter()
+                .position(|r| {
+                    sum += r.weight as u32;
+                    sum >= selected
+                })
+                .unwrap_or(0);
+            result.push(group.remove(index));
+        }
+    }
+    result
+}
+
+/// Order SRV records by priority and weight as described in RFC2782
+pub fn sort_srv_records(records: Vec<SrvRecord>) -> Vec<SrvRecord> {
+    order_srv_records(records, |n| fastrand::u32(0..=n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_srv_records() {
+        let records = vec![
+            SrvRecord::new(10, 0, 389, "backup.example.com."),
+            SrvRecord::new(0, 10, 389, "dc1.example.com."),
+            SrvRecord::new(0, 90, 389, "dc2.example.com."),
+            SrvRecord::new(0, 0, 389, "dc3.example.com."),
+            SrvRecord::new(5, 0, 389, "."),
+        ];
+
+        let targets = |records: Vec<SrvRecord>| records.into_iter().map(|r| r.target).collect::<Vec<_>>();
+
+        let sorted = order_srv_records(records.clone(), |_| 0);
+        assert_eq!(
+            targets(sorted),
+            [
+                "dc3.example.com",
+                "dc1.example.com",
+                "dc2.example.com",
+                "backup.example.com"
+            ]
+        );
+
+        let sorted = order_srv_records(records, |n| n);
+        assert_eq!(
+            targets(sorted),
+            [
+                "dc2.example.com",
+                "dc1.example.com",
+                "dc3.example.com",
+                "backup.example.com"
+            ]
+        );
+    }
+}
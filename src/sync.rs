@@ -0,0 +1,341 @@
+//! Content synchronization (RFC4533)
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use rasn::{Decode, Decoder, Encode, Encoder, ber, types::*};
+use rasn_ldap::{IntermediateResponse, ProtocolOp, ResultCode};
+
+use crate::{
+    OperationResult, SearchEntry,
+    conn::{AbandonGuard, LdapConnection, MessageStream},
+    controls::{ResponseControl, SyncDoneControl, SyncState, SyncStateControl},
+    error::Error,
+    oid,
+};
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, Eq)]
+struct RealRefreshPhase {
+    cookie: Option<OctetString>,
+    #[rasn(default = "default_true")]
+    refresh_done: bool,
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, Eq)]
+struct RealSyncIdSet {
+    cookie: Option<OctetString>,
+    #[rasn(default)]
+    refresh_deletes: bool,
+    sync_uuids: SetOf<OctetString>,
+}
+
+#[derive(AsnType, Encode, Decode, Debug, Clone, PartialEq, Eq)]
+#[rasn(choice)]
+enum RealSyncInfo {
+    #[rasn(tag(0))]
+    NewCookie(OctetString),
+    #[rasn(tag(1))]
+    RefreshDelete(RealRefreshPhase),
+    #[rasn(tag(2))]
+    RefreshPresent(RealRefreshPhase),
+    #[rasn(tag(3))]
+    SyncIdSet(RealSyncIdSet),
+}
+
+/// An entry returned by the synchronization session
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyncEntry {
+    /// Entry state
+    pub state: SyncState,
+    /// Entry UUID
+    pub entry_uuid: Vec<u8>,
+    /// The entry itself. For deleted entries only the DN is returned
+    pub entry: SearchEntry,
+}
+
+/// Synchronization event
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SyncEvent {
+    /// An entry was added, modified, deleted or is present
+    Entry(SyncEntry),
+    /// The server finished sending present entries during the refresh phase
+    RefreshPresent { done: bool },
+    /// The server finished sending deleted entries during the refresh phase
+    RefreshDelete { done: bool },
+    /// A set of entry UUIDs which are deleted if `refresh_deletes` is true, or present otherwise
+    IdSet {
+        entry_uuids: Vec<Vec<u8>>,
+        refresh_deletes: bool,
+    },
+    /// The server sent a new cookie which should be stored to resume the synchronization later
+    Cookie(Vec<u8>),
+    /// The synchronization session is finished
+    Done { refresh_deletes: bool },
+}
+
+// Convert the sync info message into an event and an optional updated cookie
+fn sync_info_event(info: RealSyncInfo) -> (SyncEvent, Option<OctetString>) {
+    match info {
+        RealSyncInfo::NewCookie(cookie) => (SyncEvent::Cookie(cookie.to_vec()), Some(cookie)),
+        RealSyncInfo::RefreshDelete(phase) => (
+            SyncEvent::RefreshDelete {
+                done: phase.refresh_done,
+            },
+            phase.cookie,
+        ),
+        RealSyncInfo::RefreshPresent(phase) => (
+            SyncEvent::RefreshPresent {
+                done: phase.refresh_done,
+            },
+            phase.cookie,
+        ),
+        RealSyncInfo::SyncIdSet(id_set) => (
+            SyncEvent::IdSet {
+                entry_uuids: id_set.sync_uuids.to_vec().into_iter().map(|u| u.to_vec()).collect(),
+                refresh_deletes: id_set.refresh_deletes,
+            },
+            id_set.cookie,
+        ),
+    }
+}
+
+/// A stream of synchronization events.
+/// Dropping the stream before it is finished abandons the synchronization session.
+pub struct SyncEntries {
+    inner: MessageStream,
+    guard: AbandonGuard,
+    cookie: Option<Vec<u8>>,
+}
+
+impl SyncEntries {
+    pub(crate) fn new(inner: MessageStream, connection: LdapConnection) -> Self {
+        Self {
+            guard: AbandonGuard::new(connection, inner.id()),
+            inner,
+            cookie: None,
+        }
+    }
+
+    /// Return the message id of the search operation
+    pub fn message_id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Return the most recent synchronization cookie which can be used to resume the synchronization later
+    pub fn cookie(&self) -> Option<&[u8]> {
+        self.cookie.as_deref()
+    }
+
+    fn update_cookie(&mut self, cookie: Option<OctetString>) {
+        if let Some(cookie) = cookie {
+            self.cookie = Some(cookie.to_vec());
+        }
+    }
+
+    fn sync_info(&mut self, resp: IntermediateResponse) -> Result<SyncEvent, Error> {
+        if resp.response_name.as_deref() != Some(oid::SYNC_INFO_OID) {
+            return Err(Error::InvalidResponse);
+        }
+
+        let info = ber::decode::<RealSyncInfo>(resp.response_value.as_deref().unwrap_or(b""))?;
+        let (event, cookie) = sync_info_event(info);
+        self.update_cookie(cookie);
+        Ok(event)
+    }
+}
+
+impl Stream for SyncEntries {
+    type Item = Result<SyncEvent, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.guard.is_done() {
+            return Poll::Ready(None);
+        }
+        loop {
+            let msg = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {
+                    self.guard.set_done();
+                    if self.inner.is_abandoned() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Err(Error::ConnectionClosed)));
+                }
+                Poll::Ready(Some(msg)) => msg,
+            };
+
+            let controls = msg.controls.unwrap_or_default();
+
+            let rc = match msg.protocol_op {
+                ProtocolOp::SearchResEntry(item) => match SyncStateControl::from_controls(&controls) {
+                    Some(Ok(state)) => {
+                        self.cookie = state.cookie.or(self.cookie.take());
+                        Some(Ok(SyncEvent::Entry(SyncEntry {
+                            state: state.state,
+                            entry_uuid: state.entry_uuid,
+                            entry: item.into(),
+                        })))
+                    }
+                    Some(Err(e)) => Some(Err(e)),
+                    None => Some(Err(Error::InvalidResponse)),
+                },
+                ProtocolOp::SearchResRef(_) => continue,
+                ProtocolOp::IntermediateResponse(resp) => Some(self.sync_info(resp)),
+                ProtocolOp::SearchResDone(done) => {
+                    self.guard.set_done();
+                    if done.0.result_code == ResultCode::Success {
+                        match SyncDoneControl::from_controls(&controls) {
+                            Some(Ok(sync_done)) => {
+                                self.cookie = sync_done.cookie.or(self.cookie.take());
+                                Some(Ok(SyncEvent::Done {
+                                    refresh_deletes: sync_done.refresh_deletes,
+                                }))
+                            }
+                            Some(Err(e)) => Some(Err(e)),
+                            None => Some(Ok(SyncEvent::Done { refresh_deletes: false })),
+                        }
+                    } else {
+                        Some(Err(Error::OperationFailed(
+                            OperationResult::new(done.0, Some(controls)).into(),
+                        )))
+                    }
+                }
+                _ => Some(Err(Error::InvalidResponse)),
+            };
+            return Poll::Ready(rc);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+    use rasn_ldap::{Control, LdapMessage, SearchResultDone, SearchResultEntry};
+
+    use super::*;
+    use crate::{
+        LdapClient, SearchRequest,
+        controls::{SyncRequestControl, SyncRequestMode},
+        mock::{MockServer, ldap_result},
+    };
+
+    #[test]
+    fn test_sync_info() {
+        let info = ber::decode::<RealSyncInfo>(b"\x80\x03abc").unwrap();
+        assert_eq!(info, RealSyncInfo::NewCookie(b"abc".as_slice().into()));
+
+        let info = ber::decode::<RealSyncInfo>(b"\xa2\x05\x04\x03abc").unwrap();
+        assert_eq!(
+            info,
+            RealSyncInfo::RefreshPresent(RealRefreshPhase {
+                cookie: Some(b"abc".as_slice().into()),
+                refresh_done: true,
+            })
+        );
+
+        let info = ber::decode::<RealSyncInfo>(b"\xa3\x0b\x01\x01\xff\x31\x06\x04\x01a\x04\x01b").unwrap();
+        match info {
+            RealSyncInfo::SyncIdSet(id_set) => {
+                assert_eq!(id_set.cookie, None);
+                assert!(id_set.refresh_deletes);
+                let mut uuids = id_set
+                    .sync_uuids
+                    .to_vec()
+                    .into_iter()
+                    .map(|u| u.to_vec())
+                    .collect::<Vec<_>>();
+                uuids.sort();
+                assert_eq!(uuids, vec![b"a".to_vec(), b"b".to_vec()]);
+            }
+            _ => panic!("Unexpected sync info"),
+        }
+    }
+
+    #[test]
+    fn test_new_cookie_event() {
+        let info = ber::decode::<RealSyncInfo>(b"\x80\x03abc").unwrap();
+        let (event, cookie) = sync_info_event(info);
+        assert_eq!(event, SyncEvent::Cookie(b"abc".to_vec()));
+        assert_eq!(cookie.as_deref(), Some(b"abc".as_slice()));
+    }
+
+    #[tokio::test]
+    async fn test_sync_stream() {
+        // Server which returns an added entry, a new cookie and the final cookie in the sync done control
+        let server = MockServer::start(|_, msg| {
+            let ProtocolOp::SearchRequest(_) = msg.protocol_op else {
+                return Vec::new();
+            };
+            let state = Control::new(
+                SyncStateControl::OID.into(),
+                false,
+                Some(b"\x30\x07\x0a\x01\x01\x04\x02u1".as_slice().into()),
+            );
+            let mut entry = LdapMessage::new(
+                msg.message_id,
+                ProtocolOp::SearchResEntry(SearchResultEntry::new("cn=user".into(), Vec::new())),
+            );
+            entry.controls = Some(vec![state]);
+            let info = IntermediateResponse {
+                response_name: Some(oid::SYNC_INFO_OID.into()),
+                response_value: Some(b"\x80\x02c1".as_slice().into()),
+            };
+            let sync_done = Control::new(
+                SyncDoneControl::OID.into(),
+                false,
+                Some(b"\x30\x04\x04\x02c2".as_slice().into()),
+            );
+            let mut done = LdapMessage::new(
+                msg.message_id,
+                ProtocolOp::SearchResDone(SearchResultDone(ldap_result(ResultCode::Success))),
+            );
+            done.controls = Some(vec![sync_done]);
+            vec![
+                entry,
+                LdapMessage::new(msg.message_id, ProtocolOp::IntermediateResponse(info)),
+                done,
+            ]
+        })
+        .await;
+
+        let mut client = LdapClient::builder("127.0.0.1")
+            .port(server.port)
+            .connect()
+            .await
+            .unwrap();
+        let mut entries = client
+            .sync(
+                SearchRequest::root_dse(),
+                SyncRequestControl::new(SyncRequestMode::RefreshOnly),
+            )
+            .await
+            .unwrap();
+
+        let Some(SyncEvent::Entry(entry)) = entries.try_next().await.unwrap() else {
+            panic!("Expected an entry");
+        };
+        assert_eq!(entry.state, SyncState::Add);
+        assert_eq!(entry.entry_uuid, b"u1");
+        assert_eq!(entry.entry.dn, "cn=user");
+
+        assert_eq!(
+            entries.try_next().await.unwrap(),
+            Some(SyncEvent::Cookie(b"c1".to_vec()))
+        );
+        assert_eq!(entries.cookie(), Some(b"c1".as_slice()));
+
+        assert_eq!(
+            entries.try_next().await.unwrap(),
+            Some(SyncEvent::Done { refresh_deletes: false })
+        );
+        assert_eq!(entries.cookie(), Some(b"c2".as_slice()));
+        assert_eq!(entries.try_next().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,406 @@
+//! LDAP URL (RFC4516)
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    client::LdapClientBuilder,
+    error::Error,
+    model::{SearchRequestDerefAliases, SearchRequestScope},
+    options::TlsOptions,
+    request::SearchRequest,
+};
+
+const DEFAULT_FILTER: &str = "(objectClass=*)";
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            result.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(result).ok()
+}
+
+// Characters which are left as is, in addition to the unreserved ones
+fn percent_encode(s: &str, keep: &[u8]) -> String {
+    let mut result = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) || keep.contains(&b) {
+            result.push(b as char);
+        } else {
+            result.push_str(&format!("%{b:02X}"));
+        }
+    }
+    result
+}
+
+const DN_CHARS: &[u8] = b"!$&'()*+,;=:@/";
+const EXT_CHARS: &[u8] = b"!$&'()*+;=:@/";
+
+/// LDAP URL scheme
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LdapUrlScheme {
+    /// Plain LDAP connection, default port 389
+    #[default]
+    Ldap,
+    /// LDAP over TLS, default port 636
+    Ldaps,
+    /// LDAP over IPC (Unix domain socket)
+    Ldapi,
+}
+
+impl LdapUrlScheme {
+    /// Return the scheme name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LdapUrlScheme::Ldap => "ldap",
+            LdapUrlScheme::Ldaps => "ldaps",
+            LdapUrlScheme::Ldapi => "ldapi",
+        }
+    }
+
+    /// Return the default port for the scheme
+    pub fn default_port(&self) -> Option<u16> {
+        match self {
+            LdapUrlScheme::Ldap => Some(389),
+            LdapUrlScheme::Ldaps => Some(636),
+            LdapUrlScheme::Ldapi => None,
+        }
+    }
+}
+
+/// LDAP URL extension
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LdapUrlExtension {
+    /// Extension criticality
+    pub critical: bool,
+    /// Extension type, typically an OID
+    pub name: String,
+    /// Extension value
+    pub value: Option<String>,
+}
+
+/// Parsed LDAP URL. Use `FromStr` to parse and `Display` to build the string representation
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LdapUrl {
+    /// URL scheme
+    pub scheme: LdapUrlScheme,
+    /// Host name, or a socket path for the `ldapi` scheme
+    pub host: Option<String>,
+    /// Port number
+    pub port: Option<u16>,
+    /// Base DN
+    pub base_dn: String,
+    /// Attributes to return
+    pub attributes: Vec<String>,
+    /// Search scope
+    pub scope: Option<SearchRequestScope>,
+    /// Search filter
+    pub filter: Option<String>,
+    /// Extensions
+    pub extensions: Vec<LdapUrlExtension>,
+}
+
+impl LdapUrl {
+    /// Return the port number or the default port for the scheme
+    pub fn port_or_default(&self) -> Option<u16> {
+        self.port.or_else(|| self.scheme.default_port())
+    }
+
+    fn parse_host(&mut self, hostport: &str) -> Option<()> {
+        if self.scheme == LdapUrlScheme::Ldapi {
+            if !hostport.is_empty() {
+                self.host = Some(percent_decode(hostport)?);
+            }
+            return Some(());
+        }
+
+        let (host, port) = match hostport.strip_prefix('[') {
+            Some(ipv6) => {
+                let (host, port) = ipv6.split_once(']')?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match hostport.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (hostport, None),
+            },
+        };
+
+        if !host.is_empty() {
+            self.host = Some(percent_decode(host)?);
+        }
+        if let Some(port) = port.filter(|p| !p.is_empty()) {
+            self.port = Some(port.parse().ok()?);
+        }
+        Some(())
+    }
+
+    fn parse_extension(ext: &str) -> Option<LdapUrlExtension> {
+        let (critical, ext) = match ext.strip_prefix('!') {
+            Some(ext) => (true, ext),
+            None => (false, ext),
+        };
+        let (name, value) = match ext.split_once('=') {
+            Some((name, value)) => (name, Some(percent_decode(value)?)),
+            None => (ext, None),
+        };
+        if name.is_empty() {
+            return None;
+        }
+        Some(LdapUrlExtension {
+            critical,
+            name: percent_decode(name)?,
+            value,
+        })
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let (scheme, rest) = s.split_once("://")?;
+        let scheme = match scheme.to_ascii_lowercase().as_str() {
+            "ldap" => LdapUrlScheme::Ldap,
+            "ldaps" => LdapUrlScheme::Ldaps,
+            "ldapi" => LdapUrlScheme::Ldapi,
+            _ => return None,
+        };
+
+        let mut url = LdapUrl {
+            scheme,
+            ..Default::default()
+        };
+
+        let (hostport, rest) = match rest.split_once('/') {
+            Some((hostport, rest)) => (hostport, Some(rest)),
+            None => (rest, None),
+        };
+        url.parse_host(hostport)?;
+
+        let Some(rest) = rest else {
+            return Some(url);
+        };
+
+        let mut parts = rest.splitn(5, '?');
+
+        url.base_dn = percent_decode(parts.next().unwrap_or_default())?;
+
+        if let Some(attributes) = parts.next().filter(|a| !a.is_empty()) {
+            url.attributes = attributes.split(',').map(percent_decode).collect::<Option<Vec<_>>>()?;
+        }
+
+        url.scope = match parts.next().unwrap_or_default().to_ascii_lowercase().as_str() {
+            "" => None,
+            "base" => Some(SearchRequestScope::BaseObject),
+            "one" => Some(SearchRequestScope::SingleLevel),
+            "sub" => Some(SearchRequestScope::WholeSubtree),
+            _ => return None,
+        };
+
+        if let Some(filter) = parts.next().filter(|f| !f.is_empty()) {
+            url.filter = Some(percent_decode(filter)?);
+        }
+
+        if let Some(extensions) = parts.next().filter(|e| !e.is_empty()) {
+            url.extensions = extensions
+                .split(',')
+                .map(Self::parse_extension)
+                .collect::<Option<Vec<_>>>()?;
+        }
+
+        Some(url)
+    }
+}
+
+impl FromStr for LdapUrl {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s).ok_or_else(|| Error::InvalidUrl(s.to_owned()))
+    }
+}
+
+impl fmt::Display for LdapUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://", self.scheme.as_str())?;
+
+        if let Some(ref host) = self.host {
+            if self.scheme == LdapUrlScheme::Ldapi {
+                write!(f, "{}", percent_encode(host, b""))?;
+            } else if host.contains(':') {
+                write!(f, "[{host}]")?;
+            } else {
+                write!(f, "{}", percent_encode(host, b""))?;
+            }
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+
+        let scope = match self.scope {
+            None => "",
+            Some(SearchRequestScope::BaseObject) => "base",
+            Some(SearchRequestScope::SingleLevel) => "one",
+            Some(SearchRequestScope::WholeSubtree) => "sub",
+            Some(_) => "",
+        };
+
+        let parts = [
+            percent_encode(&self.base_dn, DN_CHARS),
+            self.attributes
+                .iter()
+                .map(|a| percent_encode(a, b""))
+                .collect::<Vec<_>>()
+                .join(","),
+            scope.to_owned(),
+            self.filter
+                .as_ref()
+                .map(|filter| percent_encode(filter, DN_CHARS))
+                .unwrap_or_default(),
+            self.extensions
+                .iter()
+                .map(|ext| {
+                    let mut s = if ext.critical { "!".to_owned() } else { String::new() };
+                    s.push_str(&percent_encode(&ext.name, b""));
+                    if let Some(ref value) = ext.value {
+                        s.push('=');
+                        s.push_str(&percent_encode(value, EXT_CHARS));
+                    }
+                    s
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+        ];
+
+        // trailing empty parts are omitted
+        let count = parts.iter().rposition(|p| !p.is_empty()).map(|i| i + 1).unwrap_or(0);
+        if count > 0 {
+            write!(f, "/{}", parts[..count].join("?"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&LdapUrl> for LdapClientBuilder {
+    type Error = Error;
+
+    fn try_from(url: &LdapUrl) -> Result<Self, Self::Error> {
+        let (Some(host), Some(port)) = (url.host.as_ref(), url.port_or_default()) else {
+            return Err(Error::InvalidUrl(url.to_string()));
+        };
+
+        let builder = crate::LdapClient::builder(host).port(port);
+
+        Ok(if url.scheme == LdapUrlScheme::Ldaps {
+            builder.tls_options(TlsOptions::tls())
+        } else {
+            builder
+        })
+    }
+}
+
+impl TryFrom<&LdapUrl> for SearchRequest {
+    type Error = Error;
+
+    fn try_from(url: &LdapUrl) -> Result<Self, Self::Error> {
+        SearchRequest::builder()
+            .base_dn(&url.base_dn)
+            .scope(url.scope.unwrap_or(SearchRequestScope::BaseObject))
+            .deref_aliases(SearchRequestDerefAliases::NeverDerefAliases)
+            .attributes(&url.attributes)
+            .filter(url.filter.as_deref().unwrap_or(DEFAULT_FILTER))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        let url: LdapUrl = "ldap://ldap1.example.net:6666/o=University%20of%20Michigan,c=US??sub?(cn=Babs%20Jensen)"
+            .parse()
+            .unwrap();
+        assert_eq!(url.scheme, LdapUrlScheme::Ldap);
+        assert_eq!(url.host.as_deref(), Some("ldap1.example.net"));
+        assert_eq!(url.port, Some(6666));
+        assert_eq!(url.base_dn, "o=University of Michigan,c=US");
+        assert!(url.attributes.is_empty());
+        assert_eq!(url.scope, Some(SearchRequestScope::WholeSubtree));
+        assert_eq!(url.filter.as_deref(), Some("(cn=Babs Jensen)"));
+
+        let url: LdapUrl = "ldaps://[2001:db8::7]/c=GB?objectClass,cn?one".parse().unwrap();
+        assert_eq!(url.scheme, LdapUrlScheme::Ldaps);
+        assert_eq!(url.host.as_deref(), Some("2001:db8::7"));
+        assert_eq!(url.port_or_default(), Some(636));
+        assert_eq!(url.attributes, vec!["objectClass", "cn"]);
+        assert_eq!(url.scope, Some(SearchRequestScope::SingleLevel));
+
+        let url: LdapUrl = "ldap:///??sub??!bindname=cn=Manager%2co=Foo,x-ext".parse().unwrap();
+        assert_eq!(url.host, None);
+        assert_eq!(url.base_dn, "");
+        assert_eq!(
+            url.extensions,
+            vec![
+                LdapUrlExtension {
+                    critical: true,
+                    name: "bindname".to_owned(),
+                    value: Some("cn=Manager,o=Foo".to_owned()),
+                },
+                LdapUrlExtension {
+                    critical: false,
+                    name: "x-ext".to_owned(),
+                    value: None,
+                },
+            ]
+        );
+
+        let url: LdapUrl = "ldapi://%2Fvar%2Frun%2Fslapd%2Fldapi/".parse().unwrap();
+        assert_eq!(url.scheme, LdapUrlScheme::Ldapi);
+        assert_eq!(url.host.as_deref(), Some("/var/run/slapd/ldapi"));
+        assert_eq!(url.port_or_default(), None);
+
+        assert!("http://example.com".parse::<LdapUrl>().is_err());
+        assert!("ldap://host:port".parse::<LdapUrl>().is_err());
+        assert!("ldap://host/dc=example??subtree".parse::<LdapUrl>().is_err());
+        assert!("ldap://host/dc=%zz".parse::<LdapUrl>().is_err());
+    }
+
+    #[test]
+    fn test_build_url() {
+        let urls = [
+            "ldap://ldap1.example.net:6666/o=University%20of%20Michigan,c=US??sub?(cn=Babs%20Jensen)",
+            "ldaps://[2001:db8::7]/c=GB?objectClass,cn?one",
+            "ldap:///??sub??!bindname=cn=Manager%2Co=Foo,x-ext",
+            "ldapi://%2Fvar%2Frun%2Fslapd%2Fldapi",
+            "ldap://host",
+        ];
+        for url in urls {
+            assert_eq!(url.parse::<LdapUrl>().unwrap().to_string(), url);
+        }
+    }
+
+    #[test]
+    fn test_search_request() {
+        let url: LdapUrl = "ldap://host/dc=example,dc=com?cn,sn?sub?(uid=test)".parse().unwrap();
+        let request = SearchRequest::try_from(&url).unwrap();
+        let expected = SearchRequest::builder()
+            .base_dn("dc=example,dc=com")
+            .scope(SearchRequestScope::WholeSubtree)
+            .attributes(["cn", "sn"])
+            .filter("(uid=test)")
+            .build()
+            .unwrap();
+        assert_eq!(request, expected);
+
+        let url: LdapUrl = "ldap://host".parse().unwrap();
+        assert_eq!(SearchRequest::try_from(&url).unwrap(), SearchRequest::root_dse());
+    }
+}